@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::BrightnessConfig;
+
+/// Shape of the lux to brightness curve, picked by `brightness.curve`
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum CurveKind {
+    /// brightness rises with the logarithm of the light, like the eye sees it
+    Log,
+    /// straight lines between `brightness.points`
+    Piecewise,
+}
+
+/// Turns light sensor readings into a panel brightness that follows the room
+/// gradually: readings are smoothed, mapped through the curve, small changes
+/// are ignored and the panel ramps towards the result
+pub struct BrightnessControl {
+    config: BrightnessConfig,
+    /// smoothed reading, none until the first one
+    lux: Option<f32>,
+    /// brightness the panel is ramping towards
+    target: Option<f32>,
+    /// current panel brightness as a percentage
+    brightness: f32,
+}
+
+impl BrightnessControl {
+    /// `brightness` is where the panel starts, eg. `matrix.brightness`
+    pub fn new(config: &BrightnessConfig, brightness: u8) -> Self {
+        Self {
+            config: config.clone(),
+            lux: None,
+            target: None,
+            brightness: brightness as f32,
+        }
+    }
+
+    /// Brightness percentage the curve gives for `lux`, between
+    /// `min_brightness` and `max_brightness`
+    pub fn get_curve(&self, lux: f32) -> f32 {
+        let config = &self.config;
+        let (min, max) = (config.min_brightness as f32, config.max_brightness as f32);
+
+        let brightness = match config.curve {
+            CurveKind::Log => {
+                let range = (config.bright_lux / config.dark_lux).ln();
+                let position = (lux.max(config.dark_lux) / config.dark_lux).ln() / range;
+                min + position * (max - min)
+            }
+            CurveKind::Piecewise => {
+                let points = &config.points;
+                match points.iter().position(|x| x.lux > lux) {
+                    Some(0) => points[0].brightness as f32,
+                    Some(i) => {
+                        let (low, high) = (&points[i - 1], &points[i]);
+                        let position = (lux - low.lux) / (high.lux - low.lux);
+                        let (low_brightness, high_brightness) =
+                            (low.brightness as f32, high.brightness as f32);
+                        low_brightness + position * (high_brightness - low_brightness)
+                    }
+                    None => points[points.len() - 1].brightness as f32,
+                }
+            }
+        };
+        brightness.clamp(min, max)
+    }
+
+    /// Feeds in a sensor reading, `elapsed` after the last one
+    pub fn add_reading(&mut self, lux: f32, elapsed: Duration) {
+        let lux = match (self.lux, self.config.smoothing_ms) {
+            (Some(smoothed), smoothing_ms) if smoothing_ms > 0 => {
+                let time_constant = Duration::from_millis(smoothing_ms).as_secs_f32();
+                let weight = 1.0 - (-elapsed.as_secs_f32() / time_constant).exp();
+                smoothed + (lux - smoothed) * weight
+            }
+            _ => lux,
+        };
+        self.lux = Some(lux);
+
+        let curve = self.get_curve(lux);
+        match self.target {
+            Some(target) if (curve - target).abs() < self.config.hysteresis as f32 => {}
+            _ => self.target = Some(curve),
+        }
+    }
+
+    /// Ramps the panel towards the curve, returns the new brightness
+    /// percentage when it changes
+    pub fn update(&mut self, dt: Duration) -> Option<u8> {
+        let target = self.target?;
+        let before = self.get_brightness();
+
+        let step = self.config.ramp_per_second * dt.as_secs_f32();
+        self.brightness += (target - self.brightness).clamp(-step, step);
+
+        let after = self.get_brightness();
+        match after != before {
+            true => Some(after),
+            false => None,
+        }
+    }
+
+    pub fn get_brightness(&self) -> u8 {
+        self.brightness.round() as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CurvePoint;
+
+    fn get_control(config: BrightnessConfig) -> BrightnessControl {
+        BrightnessControl::new(&config, 100)
+    }
+
+    #[test]
+    fn curves_map_lux_between_min_and_max() {
+        let log = get_control(BrightnessConfig::default());
+        assert_eq!(log.get_curve(0.0), 10.0);
+        assert_eq!(log.get_curve(1.0), 10.0);
+        assert_eq!(log.get_curve(20.0).round(), 55.0);
+        assert_eq!(log.get_curve(400.0), 100.0);
+        assert_eq!(log.get_curve(100_000.0), 100.0);
+
+        let piecewise = get_control(BrightnessConfig {
+            curve: CurveKind::Piecewise,
+            max_brightness: 80,
+            points: vec![CurvePoint::new(10.0, 20), CurvePoint::new(20.0, 100)],
+            ..BrightnessConfig::default()
+        });
+        assert_eq!(piecewise.get_curve(0.0), 20.0);
+        assert_eq!(piecewise.get_curve(12.5), 40.0);
+        // clamped to max_brightness
+        assert_eq!(piecewise.get_curve(30.0), 80.0);
+    }
+
+    #[test]
+    fn readings_are_smoothed_and_small_changes_ignored() {
+        let mut control = get_control(BrightnessConfig {
+            smoothing_ms: 1000,
+            ..BrightnessConfig::default()
+        });
+        let second = Duration::from_secs(1);
+
+        control.add_reading(400.0, second);
+        assert_eq!(control.target, Some(100.0));
+
+        // the room goes dark, but the reading only falls part of the way
+        control.add_reading(0.0, second);
+        let lux = control.lux.unwrap();
+        assert!((lux - 400.0 / std::f32::consts::E).abs() < 0.1);
+
+        // flickering around the same level leaves the target alone
+        let target = control.target.unwrap();
+        control.add_reading(lux * 1.05, second);
+        assert_eq!(control.target, Some(target));
+    }
+
+    #[test]
+    fn brightness_ramps_towards_the_target() {
+        let mut control = get_control(BrightnessConfig {
+            smoothing_ms: 0,
+            ramp_per_second: 20.0,
+            ..BrightnessConfig::default()
+        });
+
+        // nothing changes before the first reading
+        assert_eq!(control.update(Duration::from_secs(1)), None);
+
+        control.add_reading(0.0, Duration::ZERO);
+        assert_eq!(control.update(Duration::from_secs(1)), Some(80));
+        assert_eq!(control.update(Duration::from_millis(10)), None);
+        assert_eq!(control.update(Duration::from_secs(10)), Some(10));
+        assert_eq!(control.update(Duration::from_secs(1)), None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use crate::config::PlaylistEntry;
+use crate::inputs::{Button, ButtonEvent, EventKind};
+use crate::screen::ScreenRegistry;
+
+/// Rotates through the `carousel.playlist` screens, each for its dwell time.
+///
+/// The carousel only runs while one of its screens is showing, so a screen
+/// outside the playlist, like region select, keeps all the buttons until it
+/// switches to a playlist screen. While running, Select pauses and resumes
+/// the rotation, Right skips to the next screen and Left goes back
+pub struct Carousel {
+    playlist: Vec<PlaylistEntry>,
+    /// index into `playlist` of the screen showing
+    position: usize,
+    /// time the current screen has been up while not paused
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Carousel {
+    /// Errors if the playlist names a screen that isn't registered
+    pub fn new(playlist: &[PlaylistEntry], screens: &ScreenRegistry) -> Result<Self, String> {
+        for entry in playlist {
+            if !screens.contains(&entry.screen) {
+                return Err(format!(
+                    "carousel.playlist: no screen named `{}`",
+                    entry.screen
+                ));
+            }
+        }
+
+        Ok(Self {
+            playlist: playlist.to_vec(),
+            position: 0,
+            elapsed: Duration::ZERO,
+            paused: false,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Follows switches made by the screens themselves. Returns whether the
+    /// current screen is part of the playlist
+    fn sync(&mut self, screens: &ScreenRegistry) -> bool {
+        let current = match screens.get_current() {
+            Some(x) => x,
+            None => return false,
+        };
+
+        if self.playlist.get(self.position).map(|x| x.screen.as_str()) == Some(current) {
+            return true;
+        }
+
+        match self.playlist.iter().position(|x| x.screen == current) {
+            Some(i) => {
+                self.position = i;
+                self.elapsed = Duration::ZERO;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to the next available screen, or the previous one when
+    /// `forward` is false. Stays put if no other screen is available
+    fn step(&mut self, screens: &mut ScreenRegistry, forward: bool) {
+        let len = self.playlist.len();
+        self.elapsed = Duration::ZERO;
+
+        for offset in 1..len {
+            let i = match forward {
+                true => (self.position + offset) % len,
+                false => (self.position + len - offset) % len,
+            };
+
+            if screens.is_available(&self.playlist[i].screen) {
+                self.position = i;
+                _ = screens.switch_to(&self.playlist[i].screen);
+                return;
+            }
+        }
+    }
+
+    /// Acts on the button events meant for the carousel and returns the
+    /// rest, to be passed on to the current screen. Only presses count,
+    /// holding Right doesn't race through the playlist
+    pub fn handle_input(
+        &mut self,
+        screens: &mut ScreenRegistry,
+        events: &[ButtonEvent],
+    ) -> Vec<ButtonEvent> {
+        if !self.sync(screens) {
+            return events.to_vec();
+        }
+
+        let mut remaining = Vec::new();
+        for event in events {
+            let pressed = event.kind == EventKind::Pressed;
+            match event.button {
+                Button::Select if pressed => self.paused = !self.paused,
+                Button::Right if pressed => self.step(screens, true),
+                Button::Left if pressed => self.step(screens, false),
+                Button::Select | Button::Right | Button::Left => {}
+                _ => remaining.push(*event),
+            }
+        }
+
+        remaining
+    }
+
+    /// Moves on once the current screen has been up for its dwell time
+    pub fn update(&mut self, screens: &mut ScreenRegistry, dt: Duration) {
+        if !self.sync(screens) || self.paused {
+            return;
+        }
+
+        self.elapsed += dt;
+        let dwell = Duration::from_millis(self.playlist[self.position].dwell_ms);
+        if self.elapsed >= dwell {
+            self.step(screens, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::events::presses;
+    use crate::matrix::FrameBuffer;
+    use crate::screen::Screen;
+
+    struct TestScreen {
+        name: &'static str,
+        available: bool,
+    }
+
+    impl Screen for TestScreen {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn is_available(&self) -> bool {
+            self.available
+        }
+
+        fn draw(&mut self, _canvas: &mut FrameBuffer) {}
+    }
+
+    /// `a`, `b` and an unavailable `c` in the playlist, plus `menu` outside it
+    fn get_carousel() -> (Carousel, ScreenRegistry) {
+        let mut screens = ScreenRegistry::new();
+        for (name, available) in [("a", true), ("b", true), ("c", false), ("menu", true)] {
+            screens.register(Box::new(TestScreen { name, available }));
+        }
+
+        let playlist = vec![
+            PlaylistEntry::new("a", 2_000),
+            PlaylistEntry::new("b", 1_000),
+            PlaylistEntry::new("c", 1_000),
+        ];
+        let carousel = Carousel::new(&playlist, &screens).unwrap();
+        screens.switch_to("a").unwrap();
+
+        (carousel, screens)
+    }
+
+    #[test]
+    fn screens_rotate_after_their_dwell_time() {
+        let (mut carousel, mut screens) = get_carousel();
+
+        carousel.update(&mut screens, Duration::from_millis(1500));
+        assert_eq!(screens.get_current(), Some("a"));
+        carousel.update(&mut screens, Duration::from_millis(500));
+        assert_eq!(screens.get_current(), Some("b"));
+
+        // `c` has nothing to show, so the rotation wraps around to `a`
+        carousel.update(&mut screens, Duration::from_secs(1));
+        assert_eq!(screens.get_current(), Some("a"));
+    }
+
+    #[test]
+    fn buttons_pause_skip_and_go_back() {
+        let (mut carousel, mut screens) = get_carousel();
+
+        let remaining =
+            carousel.handle_input(&mut screens, &presses(&[Button::Select, Button::Up]));
+        assert_eq!(remaining, presses(&[Button::Up]));
+        assert!(carousel.is_paused());
+        carousel.update(&mut screens, Duration::from_secs(10));
+        assert_eq!(screens.get_current(), Some("a"));
+
+        _ = carousel.handle_input(&mut screens, &presses(&[Button::Right]));
+        assert_eq!(screens.get_current(), Some("b"));
+        _ = carousel.handle_input(&mut screens, &presses(&[Button::Left, Button::Left]));
+        assert_eq!(screens.get_current(), Some("b"));
+        assert!(carousel.is_paused());
+    }
+
+    #[test]
+    fn screens_outside_the_playlist_keep_their_buttons() {
+        let (mut carousel, mut screens) = get_carousel();
+        screens.switch_to("menu").unwrap();
+
+        let remaining = carousel.handle_input(&mut screens, &presses(&[Button::Right]));
+        assert_eq!(remaining, presses(&[Button::Right]));
+        carousel.update(&mut screens, Duration::from_secs(10));
+        assert_eq!(screens.get_current(), Some("menu"));
+
+        // picks up from wherever the menu switches to
+        screens.switch_to("b").unwrap();
+        carousel.update(&mut screens, Duration::from_secs(1));
+        assert_eq!(screens.get_current(), Some("a"));
+    }
+
+    #[test]
+    fn unknown_screens_are_rejected() {
+        let (_, screens) = get_carousel();
+        let playlist = vec![PlaylistEntry::new("clock", 5_000)];
+
+        assert!(Carousel::new(&playlist, &screens).is_err());
+    }
+}
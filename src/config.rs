@@ -0,0 +1,673 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::brightness::CurveKind;
+use crate::inputs::bh1750::{Resolution, MTREG_DEFAULT, MTREG_MAX, MTREG_MIN};
+use crate::inputs::joystick::ADC_MAX;
+use crate::inputs::Button;
+use crate::matrix::{BackendKind, MatrixConfig};
+use crate::transition::Effect;
+use crate::weather::ProviderKind;
+
+/// Read at startup when no `--config` flag is given, if it exists
+pub static DEFAULT_CONFIG_PATH: &str = "/etc/smart-clock/config.toml";
+
+/// Keeps the weather API key out of the config file when set
+pub static API_KEY_ENV_VAR: &str = "OPENWEATHERMAP_API_KEY";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Everything that can be set in `config.toml`, see `config.example.toml`.
+/// Missing keys fall back to their defaults, unknown keys are an error
+#[derive(Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub matrix: MatrixConfig,
+    pub brightness: BrightnessConfig,
+    pub input: InputConfig,
+    pub weather: WeatherConfig,
+    pub carousel: CarouselConfig,
+    pub transitions: TransitionsConfig,
+    pub storage: StorageConfig,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// one of `hat`, `simulator`, `terminal` or `headless`, unset picks the
+    /// best backend compiled in
+    pub backend: Option<String>,
+    /// frames drawn per second, screens and timers advance in fixed steps
+    /// of `1 / fps` seconds
+    pub fps: u32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            fps: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrightnessConfig {
+    /// milliseconds between feeding light sensor readings to the curve
+    pub update_interval_ms: u64,
+    /// light sensor resolution: `high` (1 lx), `high2` (0.5 lx) or `low`
+    /// (4 lx, quicker)
+    pub sensor_mode: Resolution,
+    /// keep the sensor measuring, rather than starting a measurement for
+    /// each reading and letting it power down in between
+    pub continuous: bool,
+    /// measurement time register, 31-254. Higher values are more sensitive
+    /// but take longer to measure
+    pub sensitivity: u8,
+    /// adjust the sensitivity to the light, so very dark rooms can be told
+    /// apart without bright ones saturating the sensor
+    pub auto_range: bool,
+    /// how lux maps to brightness, `log` or `piecewise`
+    pub curve: CurveKind,
+    /// panel brightness percentages the curve is kept between
+    pub min_brightness: u8,
+    pub max_brightness: u8,
+    /// `log` curve: lux at which the panel is at its dimmest and brightest
+    pub dark_lux: f32,
+    pub bright_lux: f32,
+    /// `piecewise` curve: brightness at each light level, in order of lux,
+    /// with straight lines between them
+    pub points: Vec<CurvePoint>,
+    /// time constant of the smoothing applied to readings, 0 for none
+    pub smoothing_ms: u64,
+    /// percentage points the curve has to move by before the panel follows
+    pub hysteresis: u8,
+    /// fastest the panel brightness changes, in percentage points per second
+    pub ramp_per_second: f32,
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        Self {
+            update_interval_ms: 500,
+            sensor_mode: Resolution::High2,
+            continuous: true,
+            sensitivity: MTREG_DEFAULT,
+            auto_range: true,
+            curve: CurveKind::Log,
+            min_brightness: 10,
+            max_brightness: 100,
+            dark_lux: 1.0,
+            bright_lux: 400.0,
+            points: vec![
+                CurvePoint::new(0.0, 10),
+                CurvePoint::new(5.0, 30),
+                CurvePoint::new(50.0, 70),
+                CurvePoint::new(400.0, 100),
+            ],
+            smoothing_ms: 3000,
+            hysteresis: 3,
+            ramp_per_second: 20.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CurvePoint {
+    pub lux: f32,
+    /// percentage, 1-100
+    pub brightness: u8,
+}
+
+impl CurvePoint {
+    pub fn new(lux: f32, brightness: u8) -> Self {
+        Self { lux, brightness }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// milliseconds between reading the buttons
+    pub poll_interval_ms: u64,
+    /// BCM number of the GPIO pin wired to the joy featherwing's INT pad.
+    /// The buttons are then only read over I2C when they change, otherwise
+    /// they are polled every `poll_interval_ms`
+    pub interrupt_pin: Option<u8>,
+    /// milliseconds a button has to stay pressed or released to count,
+    /// hides contact bounce
+    pub debounce_ms: u64,
+    /// milliseconds a button is held for before a long press
+    pub long_press_ms: u64,
+    /// milliseconds a button is held for before it starts repeating
+    pub repeat_delay_ms: u64,
+    /// milliseconds between the first repeats
+    pub repeat_interval_ms: u64,
+    /// shortest interval between repeats however long a button is held
+    pub repeat_min_interval_ms: u64,
+    /// the repeat interval is multiplied by this after every repeat, 1 repeats
+    /// at a steady rate
+    pub repeat_acceleration: f64,
+    /// degrees clockwise the joy featherwing is turned from upright, 0, 90,
+    /// 180 or 270. Its directions are turned back to match the panel
+    pub rotation: u16,
+    /// which seesaw pin each featherwing button is on and what it does
+    pub buttons: Vec<ButtonMapping>,
+    pub joystick: JoystickConfig,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 50,
+            interrupt_pin: None,
+            debounce_ms: 20,
+            long_press_ms: 800,
+            repeat_delay_ms: 400,
+            repeat_interval_ms: 150,
+            repeat_min_interval_ms: 30,
+            repeat_acceleration: 0.85,
+            rotation: 0,
+            buttons: vec![
+                ButtonMapping::new("A", 6, Button::Up),
+                ButtonMapping::new("B", 7, Button::Left),
+                ButtonMapping::new("X", 10, Button::Right),
+                ButtonMapping::new("Y", 9, Button::Down),
+                ButtonMapping::new("Select", 14, Button::Select),
+            ],
+            joystick: JoystickConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonMapping {
+    /// label printed next to the button, eg. `A`
+    pub name: String,
+    /// seesaw GPIO pin the button pulls low, 0-31
+    pub pin: u8,
+    pub action: Button,
+}
+
+impl ButtonMapping {
+    pub fn new(name: &str, pin: u8, action: Button) -> Self {
+        Self {
+            name: String::from(name),
+            pin,
+            action,
+        }
+    }
+}
+
+/// Calibration of the joy featherwing's thumbstick, in raw ADC readings
+/// from 0 to 1023
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct JoystickConfig {
+    /// read the thumbstick as well as the buttons
+    pub enabled: bool,
+    /// reading with the stick let go
+    pub center_x: u16,
+    pub center_y: u16,
+    /// distance from the centre that is ignored, so a stick that doesn't
+    /// centre perfectly doesn't drift
+    pub dead_zone: u16,
+    /// distance from the centre at full deflection
+    pub range: u16,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// how far the stick has to be pushed, from 0 to 1, to hold a direction
+    /// button
+    pub button_threshold: f64,
+}
+
+impl Default for JoystickConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            center_x: 512,
+            center_y: 512,
+            dead_zone: 40,
+            range: 500,
+            invert_x: false,
+            invert_y: false,
+            button_threshold: 0.5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeatherConfig {
+    /// `openweathermap`, `open-meteo` or `fixture`
+    pub provider: ProviderKind,
+    /// OpenWeatherMap API key, overridden by the `OPENWEATHERMAP_API_KEY`
+    /// environment variable. With the `openweathermap` provider the
+    /// temperature is hidden when neither is set
+    pub api_key: Option<String>,
+    /// `metric`, `imperial` or `standard`
+    pub units: String,
+    /// two letter language code for condition descriptions
+    pub language: String,
+    /// JSON file read by the `fixture` provider
+    pub fixture_path: Option<PathBuf>,
+    /// milliseconds between successful lookups
+    pub refresh_interval_ms: u64,
+    /// milliseconds before trying again after a failed lookup
+    pub retry_interval_ms: u64,
+    /// milliseconds after which the last reading is shown as stale
+    pub stale_after_ms: u64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::OpenWeatherMap,
+            api_key: None,
+            units: String::from("metric"),
+            language: String::from("en"),
+            fixture_path: None,
+            refresh_interval_ms: 600_000,
+            retry_interval_ms: 60_000,
+            stale_after_ms: 3_600_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CarouselConfig {
+    /// screens shown in turn, empty to stay on one screen
+    pub playlist: Vec<PlaylistEntry>,
+}
+
+impl Default for CarouselConfig {
+    fn default() -> Self {
+        Self {
+            playlist: vec![
+                PlaylistEntry::new("time", 20_000),
+                PlaylistEntry::new("date", 5_000),
+                PlaylistEntry::new("weather", 10_000),
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PlaylistEntry {
+    /// name of a screen, eg. `time`
+    pub screen: String,
+    /// milliseconds the screen stays up before moving on
+    pub dwell_ms: u64,
+}
+
+impl PlaylistEntry {
+    pub fn new(screen: &str, dwell_ms: u64) -> Self {
+        Self {
+            screen: String::from(screen),
+            dwell_ms,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransitionsConfig {
+    /// effect for every switch that no rule matches
+    pub effect: Effect,
+    pub duration_ms: u64,
+    /// effects for particular switches, the first match wins
+    pub rules: Vec<TransitionRule>,
+}
+
+impl Default for TransitionsConfig {
+    fn default() -> Self {
+        Self {
+            effect: Effect::SlideLeft,
+            duration_ms: 400,
+            rules: vec![TransitionRule {
+                from: Some(String::from("region_select")),
+                to: None,
+                effect: Effect::Dissolve,
+                duration_ms: Some(600),
+            }],
+        }
+    }
+}
+
+impl TransitionsConfig {
+    /// Effect and duration for switching from the `from` screen to `to`
+    pub fn get_transition(&self, from: &str, to: &str) -> (Effect, Duration) {
+        // an unset `from` or `to` matches any screen
+        let matches = |name: &Option<String>, screen: &str| match name {
+            Some(x) => x == screen,
+            None => true,
+        };
+        let rule = self
+            .rules
+            .iter()
+            .find(|x| matches(&x.from, from) && matches(&x.to, to));
+
+        match rule {
+            Some(x) => (
+                x.effect,
+                Duration::from_millis(x.duration_ms.unwrap_or(self.duration_ms)),
+            ),
+            None => (self.effect, Duration::from_millis(self.duration_ms)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionRule {
+    /// screen being switched away from, unset matches any
+    pub from: Option<String>,
+    /// screen being switched to, unset matches any
+    pub to: Option<String>,
+    pub effect: Effect,
+    /// unset uses `transitions.duration_ms`
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// remembers the selected region across restarts
+    pub state_file: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            state_file: PathBuf::from("/var/lib/smart-clock/state.toml"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::Invalid(e) => write!(f, "invalid config: {}", e),
+        }
+    }
+}
+
+impl Config {
+    /// Loads `path`, or `DEFAULT_CONFIG_PATH` if it exists when no path is
+    /// given, otherwise returns the defaults
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let path = match path {
+            Some(x) => Some(x),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(Path::new(DEFAULT_CONFIG_PATH)),
+            None => None,
+        };
+
+        let mut config = match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+                toml::from_str(&contents).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?
+            }
+            None => Config::default(),
+        };
+
+        if let Ok(api_key) = std::env::var(API_KEY_ENV_VAR) {
+            config.weather.api_key = Some(api_key);
+        }
+        // an empty key is the same as no key
+        if config.weather.api_key.as_deref() == Some("") {
+            config.weather.api_key = None;
+        }
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Checks values that parse but are out of range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        fn check(ok: bool, message: &str) -> Result<(), ConfigError> {
+            match ok {
+                true => Ok(()),
+                false => Err(ConfigError::Invalid(String::from(message))),
+            }
+        }
+
+        if let Some(backend) = &self.display.backend {
+            backend
+                .parse::<BackendKind>()
+                .map_err(|e| ConfigError::Invalid(format!("display.backend: {}", e)))?;
+        }
+
+        check(
+            (1..=240).contains(&self.display.fps),
+            "display.fps must be between 1 and 240",
+        )?;
+
+        let matrix = &self.matrix;
+        check(matrix.cols > 0, "matrix.cols must be greater than 0")?;
+        check(matrix.rows > 0, "matrix.rows must be greater than 0")?;
+        check(
+            matrix.chain_length > 0,
+            "matrix.chain_length must be greater than 0",
+        )?;
+        check(
+            (1..=3).contains(&matrix.parallel),
+            "matrix.parallel must be between 1 and 3",
+        )?;
+        check(
+            (1..=11).contains(&matrix.pwm_bits),
+            "matrix.pwm_bits must be between 1 and 11",
+        )?;
+        check(
+            (1..=100).contains(&matrix.brightness),
+            "matrix.brightness must be between 1 and 100",
+        )?;
+        check(
+            matrix.gpio_slowdown <= 4,
+            "matrix.gpio_slowdown must be between 0 and 4",
+        )?;
+        let sequence = matrix.led_rgb_sequence.to_lowercase();
+        check(
+            sequence.len() == 3 && "rgb".chars().all(|c| sequence.contains(c)),
+            "matrix.led_rgb_sequence must be a permutation of `rgb`",
+        )?;
+
+        check(
+            self.brightness.update_interval_ms > 0,
+            "brightness.update_interval_ms must be greater than 0",
+        )?;
+        let brightness = &self.brightness;
+        check(
+            (MTREG_MIN..=MTREG_MAX).contains(&brightness.sensitivity),
+            "brightness.sensitivity must be between 31 and 254",
+        )?;
+        check(
+            (1..=100).contains(&brightness.min_brightness)
+                && (brightness.min_brightness..=100).contains(&brightness.max_brightness),
+            "brightness.min_brightness and max_brightness must be between 1 and 100, min first",
+        )?;
+        check(
+            brightness.dark_lux > 0.0 && brightness.bright_lux > brightness.dark_lux,
+            "brightness.dark_lux must be greater than 0 and less than bright_lux",
+        )?;
+        check(
+            !brightness.points.is_empty()
+                && brightness.points.windows(2).all(|x| x[0].lux < x[1].lux),
+            "brightness.points must be in increasing order of lux",
+        )?;
+        check(
+            brightness
+                .points
+                .iter()
+                .all(|x| (1..=100).contains(&x.brightness)),
+            "brightness.points brightness must be between 1 and 100",
+        )?;
+        check(
+            brightness.ramp_per_second > 0.0,
+            "brightness.ramp_per_second must be greater than 0",
+        )?;
+        check(
+            self.input.poll_interval_ms > 0,
+            "input.poll_interval_ms must be greater than 0",
+        )?;
+        check(
+            self.input.repeat_min_interval_ms > 0
+                && self.input.repeat_min_interval_ms <= self.input.repeat_interval_ms,
+            "input.repeat_min_interval_ms must be between 1 and input.repeat_interval_ms",
+        )?;
+        check(
+            self.input.repeat_acceleration > 0.0 && self.input.repeat_acceleration <= 1.0,
+            "input.repeat_acceleration must be greater than 0 and at most 1",
+        )?;
+        check(
+            [0, 90, 180, 270].contains(&self.input.rotation),
+            "input.rotation must be one of 0, 90, 180 or 270",
+        )?;
+        check(
+            self.input.buttons.iter().all(|x| x.pin < 32),
+            "input.buttons pin must be between 0 and 31",
+        )?;
+        let pins: HashSet<u8> = self.input.buttons.iter().map(|x| x.pin).collect();
+        check(
+            pins.len() == self.input.buttons.len(),
+            "input.buttons can't map the same pin twice",
+        )?;
+        let joystick = &self.input.joystick;
+        check(
+            joystick.center_x <= ADC_MAX && joystick.center_y <= ADC_MAX,
+            "input.joystick.center_x and center_y must be between 0 and 1023",
+        )?;
+        check(
+            joystick.dead_zone < joystick.range,
+            "input.joystick.dead_zone must be less than input.joystick.range",
+        )?;
+        check(
+            joystick.button_threshold > 0.0 && joystick.button_threshold <= 1.0,
+            "input.joystick.button_threshold must be greater than 0 and at most 1",
+        )?;
+
+        check(
+            ["metric", "imperial", "standard"].contains(&self.weather.units.as_str()),
+            "weather.units must be one of `metric`, `imperial` or `standard`",
+        )?;
+        check(
+            self.weather.provider != ProviderKind::Fixture || self.weather.fixture_path.is_some(),
+            "weather.fixture_path must be set for the `fixture` provider",
+        )?;
+        check(
+            self.weather.refresh_interval_ms > 0,
+            "weather.refresh_interval_ms must be greater than 0",
+        )?;
+        check(
+            self.weather.retry_interval_ms > 0,
+            "weather.retry_interval_ms must be greater than 0",
+        )?;
+        check(
+            self.carousel.playlist.iter().all(|x| x.dwell_ms > 0),
+            "carousel.playlist dwell_ms must be greater than 0",
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Makes `config` available through `get`, can only be called once
+pub fn init(config: Config) {
+    _ = CONFIG.set(config);
+}
+
+/// Configuration given to `init`, or the defaults if it was never called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_config_matches_defaults() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.example.toml");
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(
+            toml::from_str::<Config>(&contents).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let config: Config = toml::from_str("[matrix]\ncols = 128\n").unwrap();
+        assert_eq!(config.matrix.cols, 128);
+        assert_eq!(config.matrix.rows, MatrixConfig::default().rows);
+        assert_eq!(config.input, InputConfig::default());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let e = toml::from_str::<Config>("[matrix]\ncolumns = 128\n").unwrap_err();
+        assert!(e.to_string().contains("unknown field `columns`"), "{}", e);
+    }
+
+    #[test]
+    fn first_matching_transition_rule_wins() {
+        let config: TransitionsConfig = toml::from_str(
+            r#"
+            effect = "wipe"
+            duration_ms = 300
+
+            [[rules]]
+            from = "time"
+            to = "date"
+            effect = "crossfade"
+
+            [[rules]]
+            to = "date"
+            effect = "slide-up"
+            duration_ms = 500
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_transition("time", "date"),
+            (Effect::Crossfade, Duration::from_millis(300))
+        );
+        assert_eq!(
+            config.get_transition("weather", "date"),
+            (Effect::SlideUp, Duration::from_millis(500))
+        );
+        assert_eq!(
+            config.get_transition("date", "time"),
+            (Effect::Wipe, Duration::from_millis(300))
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let config: Config = toml::from_str("[matrix]\npwm_bits = 12\n").unwrap();
+        let e = config.validate().unwrap_err();
+        assert!(e.to_string().contains("matrix.pwm_bits"), "{}", e);
+    }
+}
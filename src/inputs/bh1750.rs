@@ -1,49 +1,349 @@
-use crate::inputs::InputError;
-use rppal::i2c::I2c;
-use std::sync::atomic::{AtomicU8, Ordering};
+use crate::config::BrightnessConfig;
+use crate::inputs::health::{set_health, Device, Health};
+use crate::inputs::{open_bus, I2cBus, InputError};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::thread::sleep;
 use std::time::Duration;
 
 // data sheet: https://www.mouser.com/datasheet/2/348/bh1750fvi-e-186247.pdf
 
-static BH1750_ADDR: u16 = 0x23;
-static MEASUREMENT_DELAY_MS: u64 = 150;
+static BH1750_ADDR: u8 = 0x23;
+
+/// Counts per lux at the default sensitivity
+static COUNTS_PER_LUX: f32 = 1.2;
+
+/// Measurement time register, scales the sensitivity and measurement time
+pub static MTREG_DEFAULT: u8 = 69;
+pub static MTREG_MIN: u8 = 31;
+pub static MTREG_MAX: u8 = 254;
+
+/// Auto range raises the sensitivity below this many counts, to tell a dark
+/// room from a pitch-dark one
+static LOW_COUNTS: u16 = 1000;
+/// and lowers it above this many, before the reading saturates
+static HIGH_COUNTS: u16 = 60000;
+/// Counts a sensitivity change aims for
+static TARGET_COUNTS: u32 = 20000;
+
+/// Wait between attempts to reach a sensor that stopped responding
+static RETRY_DELAY_MS: u64 = 1000;
 
 #[allow(dead_code)]
 enum OpCode {
     PowerOff = 0x0,
     PowerOn = 0x1,
     ResetMeasurement = 0x7,
-    QualityHigh = 0x20,  // medium sensitivity
-    QualityHigh2 = 0x21, // high sensitivity
-    QualityLow = 0x23,   // low sensitivity
+    ContinuousHigh = 0x10,
+    ContinuousHigh2 = 0x11,
+    ContinuousLow = 0x13,
+    QualityHigh = 0x20,  // medium sensitivity, one time
+    QualityHigh2 = 0x21, // high sensitivity, one time
+    QualityLow = 0x23,   // low sensitivity, one time
+    MTregHigh = 0x40,    // | top 3 bits of the MTreg
+    MTregLow = 0x60,     // | bottom 5 bits of the MTreg
 }
 
-static CURRENT_BRIGHTNESS: AtomicU8 = AtomicU8::new(0);
+/// Measurement mode, picked by `brightness.sensor_mode`
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolution {
+    /// 1 lx steps
+    High,
+    /// 0.5 lx steps, for dim rooms
+    High2,
+    /// 4 lx steps, but a much shorter measurement
+    Low,
+}
 
-pub struct BH1750 {}
+impl Resolution {
+    fn get_opcode(self, continuous: bool) -> u8 {
+        match (self, continuous) {
+            (Resolution::High, false) => OpCode::QualityHigh as u8,
+            (Resolution::High2, false) => OpCode::QualityHigh2 as u8,
+            (Resolution::Low, false) => OpCode::QualityLow as u8,
+            (Resolution::High, true) => OpCode::ContinuousHigh as u8,
+            (Resolution::High2, true) => OpCode::ContinuousHigh2 as u8,
+            (Resolution::Low, true) => OpCode::ContinuousLow as u8,
+        }
+    }
+
+    /// Longest a measurement can take, it grows with the MTreg
+    fn get_measurement_time(self, mtreg: u8) -> Duration {
+        let time = match self {
+            Resolution::High | Resolution::High2 => Duration::from_millis(180),
+            Resolution::Low => Duration::from_millis(24),
+        };
+        time * mtreg as u32 / MTREG_DEFAULT as u32
+    }
+
+    /// Converts a raw reading to lux, H2 counts twice per lux and a higher
+    /// MTreg counts more
+    pub fn get_lux(self, raw: u16, mtreg: u8) -> f32 {
+        let lux = raw as f32 / COUNTS_PER_LUX * MTREG_DEFAULT as f32 / mtreg as f32;
+        match self {
+            Resolution::High2 => lux / 2.0,
+            _ => lux,
+        }
+    }
+}
+
+/// f32 bits of the last reading in lux, NaN until the first one
+static CURRENT_LUX: AtomicU32 = AtomicU32::new(NO_READING);
+static NO_READING: u32 = 0x7fc0_0000;
+
+pub struct BH1750 {
+    resolution: Resolution,
+    /// keep measuring rather than powering down after each reading
+    continuous: bool,
+    auto_range: bool,
+    /// MTreg to measure with
+    mtreg: u8,
+    /// MTreg the sensor has been sent
+    sent_mtreg: Option<u8>,
+    /// whether a continuous measurement is running
+    measuring: bool,
+}
 
 impl BH1750 {
-    pub fn measure_brightness() -> Result<(), InputError> {
-        let mut channel = I2c::new().unwrap();
-        _  = channel.set_slave_address(BH1750_ADDR);
+    pub fn new(config: &BrightnessConfig) -> Self {
+        Self {
+            resolution: config.sensor_mode,
+            continuous: config.continuous,
+            auto_range: config.auto_range,
+            mtreg: config.sensitivity,
+            sent_mtreg: None,
+            measuring: false,
+        }
+    }
+
+    /// Checks the sensor answers and powers it on, `HwNotFound` otherwise
+    pub fn probe<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
+        BH1750::send(channel, OpCode::PowerOn as u8).map_err(|_| InputError::HwNotFound)
+    }
+
+    /// Probes for the sensor and measures on a separate thread from then on,
+    /// retrying if it stops responding
+    pub fn start(config: &BrightnessConfig) -> Result<(), InputError> {
+        let probed = open_bus().and_then(|mut channel| {
+            BH1750::probe(&mut channel)?;
+            Ok(channel)
+        });
+        let mut channel = match probed {
+            Ok(x) => x,
+            Err(e) => {
+                set_health(Device::LightSensor, Health::Missing);
+                return Err(e);
+            }
+        };
+        set_health(Device::LightSensor, Health::Ok);
+
+        let mut sensor = BH1750::new(config);
+        std::thread::spawn(move || loop {
+            match sensor.measure_lux(&mut channel) {
+                Ok(_) => set_health(Device::LightSensor, Health::Ok),
+                Err(_) => {
+                    set_health(Device::LightSensor, Health::Failing);
+                    sleep(Duration::from_millis(RETRY_DELAY_MS));
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn send<I2C: I2cBus>(channel: &mut I2C, opcode: u8) -> Result<(), InputError> {
+        channel
+            .write(BH1750_ADDR, &[opcode])
+            .map_err(|_| InputError::WriteErr)
+    }
+
+    /// Sends the MTreg if it changed and starts a measurement, unless a
+    /// continuous one is already running
+    fn start_measurement<I2C: I2cBus>(&mut self, channel: &mut I2C) -> Result<(), InputError> {
+        if self.sent_mtreg != Some(self.mtreg) {
+            // the measurement has to be restarted to pick it up
+            self.measuring = false;
+            BH1750::send(channel, OpCode::MTregHigh as u8 | self.mtreg >> 5)?;
+            BH1750::send(channel, OpCode::MTregLow as u8 | self.mtreg & 0x1f)?;
+            self.sent_mtreg = Some(self.mtreg);
+        }
+
+        if !self.measuring {
+            BH1750::send(channel, self.resolution.get_opcode(self.continuous))?;
+            self.measuring = self.continuous;
+        }
+        Ok(())
+    }
 
-        channel.write(&[OpCode::QualityHigh2 as u8]).unwrap();
-        sleep(Duration::from_millis(MEASUREMENT_DELAY_MS));
+    /// Takes a reading, waiting for a measurement to finish
+    pub fn measure_lux<I2C: I2cBus>(&mut self, channel: &mut I2C) -> Result<f32, InputError> {
+        let raw = match self.read_measurement(channel) {
+            Ok(x) => x,
+            Err(e) => {
+                // the sensor may have lost power, send everything again
+                self.sent_mtreg = None;
+                self.measuring = false;
+                return Err(e);
+            }
+        };
+
+        let lux = self.resolution.get_lux(raw, self.mtreg);
+        CURRENT_LUX.store(lux.to_bits(), Ordering::Relaxed);
+
+        if self.auto_range {
+            self.mtreg = BH1750::get_auto_range(raw, self.mtreg);
+        }
+
+        Ok(lux)
+    }
+
+    /// Big endian count of a finished measurement
+    fn read_measurement<I2C: I2cBus>(&mut self, channel: &mut I2C) -> Result<u16, InputError> {
+        self.start_measurement(channel)?;
+        sleep(self.resolution.get_measurement_time(self.mtreg));
 
-        // blank, brightness reading
         let mut buf: [u8; 2] = [0x0, 0x0];
-        let result = channel.read(&mut buf).unwrap();
-        if result != 2 {
-            return Err(InputError::ReadErr);
+        channel
+            .read(BH1750_ADDR, &mut buf)
+            .map_err(|_| InputError::ReadErr)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    /// MTreg for the next measurement, sensitive enough to resolve the dark
+    /// but not so sensitive bright light saturates
+    fn get_auto_range(raw: u16, mtreg: u8) -> u8 {
+        match (LOW_COUNTS..=HIGH_COUNTS).contains(&raw) {
+            true => mtreg,
+            false => {
+                let ideal = mtreg as u32 * TARGET_COUNTS / (raw as u32).max(1);
+                ideal.clamp(MTREG_MIN as u32, MTREG_MAX as u32) as u8
+            }
         }
+    }
+
+    /// Last reading, `None` before the first measurement finishes
+    pub fn get_lux() -> Option<f32> {
+        let lux = f32::from_bits(CURRENT_LUX.load(Ordering::Relaxed));
+        match lux.is_nan() {
+            true => None,
+            false => Some(lux),
+        }
+    }
+}
 
-        CURRENT_BRIGHTNESS.store(buf[1], Ordering::Relaxed);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::mock_i2c::MockBus;
 
-        Ok(())
+    fn assert_lux(lux: f32, expected: f32) {
+        assert!(
+            (lux - expected).abs() < 0.01,
+            "{} lux, not {}",
+            lux,
+            expected
+        );
+    }
+
+    /// The quick low resolution, so tests don't wait long
+    fn get_sensor(continuous: bool, auto_range: bool) -> BH1750 {
+        BH1750::new(&BrightnessConfig {
+            sensor_mode: Resolution::Low,
+            continuous,
+            auto_range,
+            ..BrightnessConfig::default()
+        })
     }
 
-    pub fn get_brightness() -> u8 {
-        return CURRENT_BRIGHTNESS.load(Ordering::Relaxed);
+    #[test]
+    fn counts_convert_to_lux() {
+        assert_lux(Resolution::High.get_lux(120, MTREG_DEFAULT), 100.0);
+        assert_lux(Resolution::High2.get_lux(120, MTREG_DEFAULT), 50.0);
+        assert_lux(Resolution::Low.get_lux(120, MTREG_DEFAULT), 100.0);
+        assert_lux(Resolution::High.get_lux(120, MTREG_DEFAULT * 2), 50.0);
+        // the high byte counts, readings above 255 no longer wrap
+        assert_lux(Resolution::High.get_lux(0xffff, MTREG_DEFAULT), 54612.5);
+    }
+
+    #[test]
+    fn one_time_measurements_start_every_reading() {
+        let bus = MockBus::new().with_bh1750(100.0);
+        let mut sensor = get_sensor(false, false);
+
+        assert_lux(sensor.measure_lux(&mut bus.clone()).unwrap(), 100.0);
+        assert_lux(sensor.measure_lux(&mut bus.clone()).unwrap(), 100.0);
+
+        // MTreg 69 split into its high and low bits, then two measurements
+        assert_eq!(bus.bh1750().opcodes, vec![0x42, 0x65, 0x23, 0x23]);
+        assert!(!bus.bh1750().is_continuous());
+    }
+
+    #[test]
+    fn continuous_measurement_starts_once() {
+        let bus = MockBus::new().with_bh1750(100.0);
+        let mut sensor = get_sensor(true, false);
+
+        sensor.measure_lux(&mut bus.clone()).unwrap();
+        bus.set_lux(50.0);
+        assert_lux(sensor.measure_lux(&mut bus.clone()).unwrap(), 50.0);
+
+        assert_eq!(bus.bh1750().opcodes, vec![0x42, 0x65, 0x13]);
+        assert!(bus.bh1750().is_continuous());
+    }
+
+    #[test]
+    fn auto_range_follows_the_light() {
+        let bus = MockBus::new().with_bh1750(2.0);
+        let mut sensor = get_sensor(true, true);
+
+        // too few counts to resolve, so the sensitivity goes to the top
+        sensor.measure_lux(&mut bus.clone()).unwrap();
+        assert_eq!(sensor.mtreg, MTREG_MAX);
+        assert_eq!(sensor.measure_lux(&mut bus.clone()).unwrap().round(), 2.0);
+        assert_eq!(bus.bh1750().mtreg, MTREG_MAX);
+        // restarted to pick up the new MTreg
+        assert_eq!(bus.bh1750().opcodes[3..], [0x47, 0x7e, 0x13]);
+
+        // bright light saturates it until the sensitivity comes down
+        bus.set_lux(50000.0);
+        sensor.measure_lux(&mut bus.clone()).unwrap();
+        sensor.measure_lux(&mut bus.clone()).unwrap();
+        assert_eq!(sensor.mtreg, MTREG_MIN);
+        let lux = sensor.measure_lux(&mut bus.clone()).unwrap();
+        assert!((lux - 50000.0).abs() < 5.0);
+        assert_eq!(sensor.mtreg, MTREG_MIN);
+    }
+
+    #[test]
+    fn failures_are_reported_and_the_setup_resent() {
+        let bus = MockBus::new().with_bh1750(100.0);
+        let mut sensor = get_sensor(true, false);
+
+        bus.fail_writes(1);
+        assert!(matches!(
+            sensor.measure_lux(&mut bus.clone()),
+            Err(InputError::WriteErr)
+        ));
+        bus.fail_reads(1);
+        assert!(matches!(
+            sensor.measure_lux(&mut bus.clone()),
+            Err(InputError::ReadErr)
+        ));
+        assert_eq!(bus.bh1750().opcodes, vec![0x42, 0x65, 0x13]);
+        assert_lux(sensor.measure_lux(&mut bus.clone()).unwrap(), 100.0);
+        // set up again after the failed read
+        assert_eq!(bus.bh1750().opcodes[3..], [0x42, 0x65, 0x13]);
+
+        // no sensor on the bus at all
+        assert!(matches!(
+            get_sensor(true, false).measure_lux(&mut MockBus::new()),
+            Err(InputError::WriteErr)
+        ));
+        assert!(matches!(
+            BH1750::probe(&mut MockBus::new()),
+            Err(InputError::HwNotFound)
+        ));
+        assert!(BH1750::probe(&mut bus.clone()).is_ok());
     }
 }
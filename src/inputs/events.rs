@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::Button;
+use crate::config::InputConfig;
+
+/// What happened to a button
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    #[default]
+    Pressed,
+    Released,
+    /// held for `input.long_press_ms`, sent once per press
+    LongPress,
+    /// still held, sent faster the longer the button is held
+    Repeat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ButtonEvent {
+    pub button: Button,
+    pub kind: EventKind,
+}
+
+impl ButtonEvent {
+    pub fn pressed(button: Button) -> Self {
+        Self {
+            button,
+            kind: EventKind::Pressed,
+        }
+    }
+}
+
+/// One press of each button, eg. for tests
+#[cfg(test)]
+pub fn presses(buttons: &[Button]) -> Vec<ButtonEvent> {
+    buttons.iter().map(|x| ButtonEvent::pressed(*x)).collect()
+}
+
+#[derive(Clone, Copy, Default)]
+struct ButtonState {
+    /// debounced state
+    held: bool,
+    /// how long the raw state has disagreed with `held`
+    changing_for: Option<Duration>,
+    /// how long the button has been held since it was pressed
+    held_for: Duration,
+    long_pressed: bool,
+    /// `held_for` at which the next repeat is sent
+    next_repeat: Duration,
+    repeat_interval: Duration,
+}
+
+/// Turns buttons that are held or not, eg. read from GPIO pins, into
+/// debounced press, release, long press and repeat events
+pub struct ButtonTracker {
+    debounce: Duration,
+    long_press: Duration,
+    repeat_delay: Duration,
+    repeat_interval: Duration,
+    repeat_min_interval: Duration,
+    repeat_acceleration: f64,
+    /// indexed by `Button as usize`
+    states: [ButtonState; Button::ALL.len()],
+}
+
+impl ButtonTracker {
+    pub fn new(config: &InputConfig) -> Self {
+        Self {
+            debounce: Duration::from_millis(config.debounce_ms),
+            long_press: Duration::from_millis(config.long_press_ms),
+            repeat_delay: Duration::from_millis(config.repeat_delay_ms),
+            repeat_interval: Duration::from_millis(config.repeat_interval_ms),
+            repeat_min_interval: Duration::from_millis(config.repeat_min_interval_ms),
+            repeat_acceleration: config.repeat_acceleration,
+            states: [ButtonState::default(); Button::ALL.len()],
+        }
+    }
+
+    /// Whether every button is released and settled, so no event can come
+    /// until one changes
+    pub fn is_idle(&self) -> bool {
+        self.states
+            .iter()
+            .all(|x| !x.held && x.changing_for.is_none())
+    }
+
+    /// `held` are the buttons down right now, `elapsed` the time since the
+    /// last update
+    pub fn update(&mut self, held: &[Button], elapsed: Duration) -> Vec<ButtonEvent> {
+        let mut events = Vec::new();
+
+        for button in Button::ALL {
+            let is_held = held.contains(&button);
+            let state = &mut self.states[button as usize];
+
+            // the raw state has to stay put for the debounce time to count
+            state.changing_for = match (is_held == state.held, state.changing_for) {
+                (true, _) => None,
+                (false, None) => Some(Duration::ZERO),
+                (false, Some(x)) => Some(x + elapsed),
+            };
+            if matches!(state.changing_for, Some(x) if x >= self.debounce) {
+                state.held = is_held;
+                state.changing_for = None;
+
+                let kind = match is_held {
+                    true => {
+                        state.held_for = Duration::ZERO;
+                        state.long_pressed = false;
+                        state.next_repeat = self.repeat_delay;
+                        state.repeat_interval = self.repeat_interval;
+                        EventKind::Pressed
+                    }
+                    false => EventKind::Released,
+                };
+                events.push(ButtonEvent { button, kind });
+                continue;
+            }
+
+            if !state.held {
+                continue;
+            }
+
+            state.held_for += elapsed;
+            if !state.long_pressed && state.held_for >= self.long_press {
+                state.long_pressed = true;
+                events.push(ButtonEvent {
+                    button,
+                    kind: EventKind::LongPress,
+                });
+            }
+
+            // each repeat comes sooner than the last, down to the minimum
+            while state.held_for >= state.next_repeat {
+                events.push(ButtonEvent {
+                    button,
+                    kind: EventKind::Repeat,
+                });
+                state.next_repeat += state.repeat_interval;
+                state.repeat_interval = state
+                    .repeat_interval
+                    .mul_f64(self.repeat_acceleration)
+                    .max(self.repeat_min_interval);
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> ButtonTracker {
+        ButtonTracker::new(&InputConfig {
+            debounce_ms: 20,
+            long_press_ms: 1000,
+            repeat_delay_ms: 400,
+            repeat_interval_ms: 200,
+            repeat_min_interval_ms: 50,
+            repeat_acceleration: 0.5,
+            ..InputConfig::default()
+        })
+    }
+
+    fn kinds(events: &[ButtonEvent]) -> Vec<EventKind> {
+        events.iter().map(|x| x.kind).collect()
+    }
+
+    #[test]
+    fn bounces_are_ignored() {
+        let mut tracker = tracker();
+        let step = Duration::from_millis(10);
+
+        // contacts chatter for a couple of reads before settling
+        assert!(tracker.is_idle());
+        assert_eq!(tracker.update(&[Button::Up], step), vec![]);
+        assert!(!tracker.is_idle());
+        assert_eq!(tracker.update(&[], step), vec![]);
+        assert_eq!(tracker.update(&[Button::Up], step), vec![]);
+        assert_eq!(tracker.update(&[Button::Up], step), vec![]);
+        assert_eq!(
+            tracker.update(&[Button::Up], step),
+            vec![ButtonEvent::pressed(Button::Up)]
+        );
+
+        assert_eq!(tracker.update(&[], step), vec![]);
+        assert_eq!(tracker.update(&[], step), vec![]);
+        assert_eq!(
+            tracker.update(&[], step),
+            vec![ButtonEvent {
+                button: Button::Up,
+                kind: EventKind::Released,
+            }]
+        );
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn holding_repeats_faster_and_long_presses_once() {
+        let mut tracker = tracker();
+        let step = Duration::from_millis(10);
+
+        for _ in 0..3 {
+            _ = tracker.update(&[Button::Down], step);
+        }
+
+        // after pressing, record how long into the hold each event came
+        let mut held_ms = 0;
+        let mut repeats = Vec::new();
+        let mut long_presses = Vec::new();
+        while held_ms < 1500 {
+            held_ms += 10;
+            for event in tracker.update(&[Button::Down], step) {
+                match event.kind {
+                    EventKind::Repeat => repeats.push(held_ms),
+                    EventKind::LongPress => long_presses.push(held_ms),
+                    _ => panic!("unexpected {:?}", event),
+                }
+            }
+        }
+
+        assert_eq!(&repeats[..5], &[400, 600, 700, 750, 800]);
+        assert!(repeats.windows(2).skip(3).all(|x| x[1] - x[0] == 50));
+        assert_eq!(long_presses, vec![1000]);
+    }
+
+    #[test]
+    fn no_debounce_is_immediate() {
+        let mut tracker = ButtonTracker::new(&InputConfig {
+            debounce_ms: 0,
+            ..InputConfig::default()
+        });
+
+        let events = tracker.update(&[Button::Select, Button::Left], Duration::ZERO);
+        assert_eq!(kinds(&events), vec![EventKind::Pressed, EventKind::Pressed]);
+        let events = tracker.update(&[Button::Left], Duration::ZERO);
+        assert_eq!(
+            events,
+            vec![ButtonEvent {
+                button: Button::Select,
+                kind: EventKind::Released,
+            }]
+        );
+    }
+}
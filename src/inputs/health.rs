@@ -0,0 +1,46 @@
+use std::sync::Mutex;
+
+/// The I2C devices the clock looks for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Device {
+    LightSensor,
+    JoyFeatherwing,
+}
+
+impl Device {
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Device::LightSensor => "light sensor",
+            Device::JoyFeatherwing => "joy featherwing",
+        }
+    }
+}
+
+/// What the startup probe found, kept up to date as the device is used
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Health {
+    /// not probed yet
+    Unknown,
+    Ok,
+    /// didn't answer the probe, the clock runs without it
+    Missing,
+    /// answered the probe but has stopped responding since, it is retried
+    Failing,
+}
+
+lazy_static! {
+    /// indexed by `Device`
+    static ref HEALTH: Mutex<[Health; 2]> = Mutex::new([Health::Unknown; 2]);
+}
+
+pub fn get_health(device: Device) -> Health {
+    HEALTH.lock().unwrap()[device as usize]
+}
+
+/// Records a device's health, logging when it changes after the probe
+pub fn set_health(device: Device, health: Health) {
+    let previous = std::mem::replace(&mut HEALTH.lock().unwrap()[device as usize], health);
+    if previous != health && previous != Health::Unknown {
+        eprintln!("{}: {:?}", device.get_name(), health);
+    }
+}
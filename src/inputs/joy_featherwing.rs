@@ -1,54 +1,42 @@
-use crate::inputs::InputError;
-use rppal::i2c::I2c;
-use std::sync::atomic::AtomicBool;
+use crate::config;
+use crate::inputs::events::ButtonTracker;
+use crate::inputs::health::{set_health, Device, Health};
+use crate::inputs::joystick::Joystick;
+use crate::inputs::mapping::ButtonMap;
+use crate::inputs::{open_bus, Button, ButtonEvent, I2cBus, InputError, InputSource};
+use rppal::gpio::{Gpio, InputPin, Trigger};
+use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
+use std::sync::Mutex;
 use std::thread::sleep;
-use std::time::Duration;
-
-use crate::inputs::u32_to_u8s;
-use crate::inputs::u8s_to_u32;
-
-pub enum Button {
-    Up,
-    Down,
-    Left,
-    Right,
-    Select,
-}
+use std::time::{Duration, Instant};
 
 // data sheet: https://cdn-learn.adafruit.com/downloads/pdf/adafruit-seesaw-atsamd09-breakout.pdf
 // note: arduino must be read from in 32 byte chunks
+// note: pin bitmasks are sent and read big endian, bit n is seesaw pin n
 
-static JOY_I2C_ADDR: u16 = 0x49;
+static JOY_I2C_ADDR: u8 = 0x49;
 static DELAY_MS: u64 = 10;
-
-#[allow(dead_code)]
-enum JoyInternalGPIOPins {
-    /// ButtonRight = 6,
-    ButtonA = 30,
-    /// ButtonDown = 7,
-    ButtonB = 31,
-    /// ButtonLeft = 9,
-    ButtonY = 18,
-    /// ButtonUp = 10,
-    ButtonX = 17,
-    /// ButtonSelect = 14
-    ButtonSelect = 22,
+/// Writes a reset gets before the featherwing is given up on
+static RESET_ATTEMPTS: usize = 5;
+/// Wait between attempts to reach a featherwing that stopped responding
+static RETRY_DELAY_MS: u64 = 1000;
+
+/// ADC channels of the thumbstick, seesaw pins 2 and 3
+enum JoyADCChannels {
+    StickX = 0,
+    StickY = 1,
 }
 
-static JOY_BUTTON_PIN_BITMASK: [u32; 1] = [(1 << JoyInternalGPIOPins::ButtonA as u8)
-    | (1 << JoyInternalGPIOPins::ButtonB as u8)
-    | (1 << JoyInternalGPIOPins::ButtonX as u8)
-    | (1 << JoyInternalGPIOPins::ButtonY as u8)
-    | (1 << JoyInternalGPIOPins::ButtonSelect as u8)];
-
-#[allow(dead_code)]
+// names follow the seesaw register map
+#[allow(dead_code, clippy::upper_case_acronyms)]
 enum BaseRegister {
     STATUS = 0x00,
     GPIO = 0x01,
+    ADC = 0x09,
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 enum StatusFunctionRegister {
     HWID = 0x01,
     VERSION = 0x02,
@@ -57,7 +45,7 @@ enum StatusFunctionRegister {
     SWRST = 0x7f,
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 enum GPIOFunctionRegister {
     DIRSET = 0x02,
     DIRCLR = 0x03,
@@ -72,17 +60,35 @@ enum GPIOFunctionRegister {
     PULLENCLR = 0x0C,
 }
 
+#[allow(dead_code, clippy::upper_case_acronyms)]
+enum ADCFunctionRegister {
+    STATUS = 0x00,
+    INTEN = 0x02,
+    INTENCLR = 0x03,
+    WINMODE = 0x04,
+    WINTHRESH = 0x05,
+    /// add the channel number, reads back 2 bytes big endian
+    CHANNELOFFSET = 0x07,
+}
+
 #[allow(dead_code)]
-enum HardwareID {
+pub enum HardwareID {
     SAMD09 = 0x55,
     TINY8X7 = 0x87,
 }
 
-static BUTTON_A_PRESSED: AtomicBool = AtomicBool::new(false);
-static BUTTON_B_PRESSED: AtomicBool = AtomicBool::new(false);
-static BUTTON_X_PRESSED: AtomicBool = AtomicBool::new(false);
-static BUTTON_Y_PRESSED: AtomicBool = AtomicBool::new(false);
-static BUTTON_SELECT_PRESSED: AtomicBool = AtomicBool::new(false);
+/// bitmask of the mapped seesaw pins that are held
+static HELD_PINS: AtomicU32 = AtomicU32::new(0);
+
+lazy_static! {
+    /// `input.buttons` and `input.rotation`
+    static ref BUTTON_MAP: ButtonMap = ButtonMap::new(&config::get().input);
+    /// events since the last poll, queued by the reading thread so a quick
+    /// tap between polls isn't missed
+    static ref EVENTS: Mutex<Vec<ButtonEvent>> = Mutex::new(Vec::new());
+    /// calibrated thumbstick position, see `Joystick::get_position`
+    static ref STICK_POSITION: Mutex<(f32, f32)> = Mutex::new((0.0, 0.0));
+}
 
 /* Example usage:
 let buttons = JoyFeatherwing::get_joy_buttons();
@@ -101,62 +107,63 @@ pub struct JoyFeatherwing {}
 
 impl JoyFeatherwing {
     /// Resets all seesaw registers to their default values
-    fn software_reset() {
-        let mut channel = I2c::new().unwrap();
-        _ = channel.set_slave_address(JOY_I2C_ADDR);
-
-        let mut written = false;
-        while !written {
-            if let Ok(_) = channel.write(&[
-                BaseRegister::STATUS as u8,
-                StatusFunctionRegister::SWRST as u8,
-                0xFF, // no idea what this is
-            ]) {
-                written = true;
+    fn software_reset<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
+        let mut attempts = 0;
+        while channel
+            .write(
+                JOY_I2C_ADDR,
+                &[
+                    BaseRegister::STATUS as u8,
+                    StatusFunctionRegister::SWRST as u8,
+                    0xFF, // no idea what this is
+                ],
+            )
+            .is_err()
+        {
+            attempts += 1;
+            if attempts == RESET_ATTEMPTS {
+                return Err(InputError::WriteErr);
             }
         }
 
         sleep(Duration::from_millis(DELAY_MS));
+
+        Ok(())
     }
 
     /// Determines the seesaw's chipset
-    #[allow(dead_code)]
-    fn hardware_id() -> Result<HardwareID, InputError> {
-        let mut channel = I2c::new().unwrap();
-        _ = channel.set_slave_address(JOY_I2C_ADDR);
-
-        match channel.write(&[
-            BaseRegister::STATUS as u8,
-            StatusFunctionRegister::HWID as u8,
-            0xFF, // no idea what this is
-        ]) {
+    fn hardware_id<I2C: I2cBus>(channel: &mut I2C) -> Result<HardwareID, InputError> {
+        match channel.write(
+            JOY_I2C_ADDR,
+            &[
+                BaseRegister::STATUS as u8,
+                StatusFunctionRegister::HWID as u8,
+                0xFF, // no idea what this is
+            ],
+        ) {
             Ok(_) => {}
             Err(_) => return Err(InputError::WriteErr),
         }
         sleep(Duration::from_millis(DELAY_MS));
 
         let mut buf: [u8; 1] = [0x0];
-        match channel.read(&mut buf) {
-            Ok(1) => {}
+        match channel.read(JOY_I2C_ADDR, &mut buf) {
+            Ok(_) => {}
             Err(_) => return Err(InputError::ReadErr),
-            Ok(_) => return Err(InputError::ReadErr),
         }
 
         match buf[0] {
-            x if x == HardwareID::SAMD09 as u8 => return Ok(HardwareID::SAMD09),
-            x if x == HardwareID::TINY8X7 as u8 => return Ok(HardwareID::TINY8X7),
-            _ => return Err(InputError::ReadErr),
+            x if x == HardwareID::SAMD09 as u8 => Ok(HardwareID::SAMD09),
+            x if x == HardwareID::TINY8X7 as u8 => Ok(HardwareID::TINY8X7),
+            _ => Err(InputError::ReadErr),
         }
     }
 
-    fn pullup_pins() -> Result<(), InputError> {
-        let mut channel = I2c::new().unwrap();
-        _ = channel.set_slave_address(JOY_I2C_ADDR);
-
+    fn pullup_pins<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
         // dirclr - set pins to INPUT
-        match channel.write(&{
+        match channel.write(JOY_I2C_ADDR, &{
             let left = [BaseRegister::GPIO as u8, GPIOFunctionRegister::DIRCLR as u8];
-            let right = u32_to_u8s(&JOY_BUTTON_PIN_BITMASK);
+            let right = BUTTON_MAP.get_pin_bitmask().to_be_bytes();
             let whole: [u8; 6] = {
                 let mut whole: [u8; 6] = [0; 6];
                 let (one, two) = whole.split_at_mut(left.len());
@@ -172,12 +179,12 @@ impl JoyFeatherwing {
         sleep(Duration::from_millis(DELAY_MS));
 
         // pullenset - enables PULLUP/PULLDOWN depending on high/low
-        match channel.write(&{
+        match channel.write(JOY_I2C_ADDR, &{
             let left = [
                 BaseRegister::GPIO as u8,
                 GPIOFunctionRegister::PULLENSET as u8,
             ];
-            let right = u32_to_u8s(&JOY_BUTTON_PIN_BITMASK);
+            let right = BUTTON_MAP.get_pin_bitmask().to_be_bytes();
             let whole: [u8; 6] = {
                 let mut whole: [u8; 6] = [0; 6];
                 let (one, two) = whole.split_at_mut(left.len());
@@ -194,9 +201,9 @@ impl JoyFeatherwing {
 
         // set - set pins to HIGH
         // xclr - set pins to LOW
-        match channel.write(&{
+        match channel.write(JOY_I2C_ADDR, &{
             let left = [BaseRegister::GPIO as u8, GPIOFunctionRegister::SET as u8];
-            let right = u32_to_u8s(&JOY_BUTTON_PIN_BITMASK);
+            let right = BUTTON_MAP.get_pin_bitmask().to_be_bytes();
             let whole: [u8; 6] = {
                 let mut whole: [u8; 6] = [0; 6];
                 let (one, two) = whole.split_at_mut(left.len());
@@ -214,126 +221,396 @@ impl JoyFeatherwing {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    fn set_gpio_interupts() -> Result<(), InputError> {
-        let mut channel = I2c::new().unwrap();
-        _ = channel.set_slave_address(JOY_I2C_ADDR);
-
+    /// Makes the seesaw pull its INT pin low whenever a button changes
+    fn set_gpio_interupts<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
         // intenset
-        channel
-            .write(&{
-                let left = [
-                    BaseRegister::GPIO as u8,
-                    GPIOFunctionRegister::INTENSET as u8,
-                ];
-                let right = u32_to_u8s(&JOY_BUTTON_PIN_BITMASK);
-                let whole: [u8; 6] = {
-                    let mut whole: [u8; 6] = [0; 6];
-                    let (one, two) = whole.split_at_mut(left.len());
-                    one.copy_from_slice(&left);
-                    two.copy_from_slice(&right);
-                    whole
-                };
+        match channel.write(JOY_I2C_ADDR, &{
+            let left = [
+                BaseRegister::GPIO as u8,
+                GPIOFunctionRegister::INTENSET as u8,
+            ];
+            let right = BUTTON_MAP.get_pin_bitmask().to_be_bytes();
+            let whole: [u8; 6] = {
+                let mut whole: [u8; 6] = [0; 6];
+                let (one, two) = whole.split_at_mut(left.len());
+                one.copy_from_slice(&left);
+                two.copy_from_slice(&right);
                 whole
-            })
-            .unwrap();
+            };
+            whole
+        }) {
+            Ok(_) => {}
+            Err(_) => return Err(InputError::WriteErr),
+        };
         sleep(Duration::from_millis(DELAY_MS));
 
         Ok(())
     }
 
-    pub fn init() {
-        // clean registers
-        JoyFeatherwing::software_reset();
+    /// Reading the interrupt flags lets the seesaw release its INT pin
+    fn clear_gpio_interrupts<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
+        match channel.write(
+            JOY_I2C_ADDR,
+            &[
+                BaseRegister::GPIO as u8,
+                GPIOFunctionRegister::INTFLAG as u8,
+            ],
+        ) {
+            Ok(_) => {}
+            Err(_) => return Err(InputError::WriteErr),
+        }
+        sleep(Duration::from_millis(DELAY_MS));
+
+        let mut buf: [u8; 4] = [0x0; 4];
+        match channel.read(JOY_I2C_ADDR, &mut buf) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(InputError::ReadErr),
+        }
+    }
+
+    /// Watches the Pi GPIO pin wired to the seesaw's INT pin, which is
+    /// pulled low while a button change hasn't been read
+    fn watch_interrupt(pin: u8) -> Result<InputPin, InputError> {
+        let gpio = Gpio::new().map_err(|_| InputError::HwNotFound)?;
+        let mut pin = gpio
+            .get(pin)
+            .map_err(|_| InputError::HwNotFound)?
+            .into_input_pullup();
+        pin.set_interrupt(Trigger::FallingEdge)
+            .map_err(|_| InputError::HwNotFound)?;
+
+        Ok(pin)
+    }
+
+    /// Checks a seesaw answers with a known chipset, `HwNotFound` otherwise
+    pub fn probe<I2C: I2cBus>(channel: &mut I2C) -> Result<HardwareID, InputError> {
+        JoyFeatherwing::hardware_id(channel).map_err(|_| InputError::HwNotFound)
+    }
+
+    /// Probes for the featherwing, sets it up and reads its buttons on a
+    /// separate thread from then on, turning them into events. With
+    /// `input.interrupt_pin` set the buttons are only read when the seesaw
+    /// signals a change, otherwise they are polled every
+    /// `input.poll_interval_ms`
+    pub fn start() -> Result<Self, InputError> {
+        let probed = open_bus().and_then(|mut channel| {
+            JoyFeatherwing::probe(&mut channel)?;
+            JoyFeatherwing::init(&mut channel)?;
+            Ok(channel)
+        });
+        let mut channel = match probed {
+            Ok(x) => x,
+            Err(e) => {
+                set_health(Device::JoyFeatherwing, Health::Missing);
+                return Err(e);
+            }
+        };
+        set_health(Device::JoyFeatherwing, Health::Ok);
+
+        let input_config = &config::get().input;
+        let mut interrupt = match input_config.interrupt_pin {
+            Some(x) => match JoyFeatherwing::watch_interrupt(x) {
+                Ok(pin) => Some(pin),
+                Err(e) => {
+                    eprintln!(
+                        "joy featherwing interrupt on GPIO {} unavailable, polling instead: {:?}",
+                        x, e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        std::thread::spawn(move || {
+            let mut tracker = ButtonTracker::new(input_config);
+            let joystick = match input_config.joystick.enabled {
+                true => Some(Joystick::new(&input_config.joystick)),
+                false => None,
+            };
+            let mut last_read = Instant::now();
+            let mut healthy = true;
+
+            loop {
+                // held or settling buttons still need reads every DELAY_MS to
+                // time their repeats, otherwise the buttons are only read when
+                // INT fires, or every poll interval without it
+                let busy = !tracker.is_idle();
+                let read_buttons = match (&mut interrupt, healthy) {
+                    // a featherwing that stopped responding can't interrupt
+                    (Some(pin), true) => {
+                        // the stick can't interrupt, so it is read every poll
+                        // interval
+                        let timeout = match (busy, &joystick) {
+                            (true, _) => Some(Duration::from_millis(DELAY_MS)),
+                            (false, Some(_)) => {
+                                Some(Duration::from_millis(input_config.poll_interval_ms))
+                            }
+                            (false, None) => None,
+                        };
+                        // a change may already be waiting, with INT low
+                        let fired = pin.is_low()
+                            || matches!(pin.poll_interrupt(true, timeout), Ok(Some(_)));
+                        if fired {
+                            _ = JoyFeatherwing::clear_gpio_interrupts(&mut channel);
+                        }
+                        fired || busy
+                    }
+                    _ => {
+                        let delay_ms = match busy {
+                            true => DELAY_MS,
+                            false => input_config.poll_interval_ms,
+                        };
+                        sleep(Duration::from_millis(delay_ms));
+                        true
+                    }
+                };
+
+                if read_buttons {
+                    let health = match JoyFeatherwing::measure_joy_buttons(&mut channel) {
+                        Ok(_) => Health::Ok,
+                        Err(_) => Health::Failing,
+                    };
+                    healthy = health == Health::Ok;
+                    set_health(Device::JoyFeatherwing, health);
+                }
+                let mut held = JoyFeatherwing::get_joy_buttons();
 
-        // check that featherwing returns valid hardware id
-        //_ = JoyFeatherwing::hardware_id().unwrap();
+                // pushing the stick holds the arrow buttons too
+                if let (Some(joystick), true) = (&joystick, healthy) {
+                    JoyFeatherwing::measure_stick(&mut channel, joystick);
+                    held.extend(joystick.get_buttons(JoyFeatherwing::get_stick()));
+                }
+
+                let now = Instant::now();
+                let events = tracker.update(&held, now - last_read);
+                last_read = now;
+                EVENTS.lock().unwrap().extend(events);
+
+                if !healthy {
+                    sleep(Duration::from_millis(RETRY_DELAY_MS));
+                }
+            }
+        });
+
+        Ok(JoyFeatherwing {})
+    }
+
+    pub fn init<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
+        // clean registers
+        JoyFeatherwing::software_reset(channel)?;
 
         // pull-up buttons with PULLENSET
-        _ = JoyFeatherwing::pullup_pins().unwrap();
+        JoyFeatherwing::pullup_pins(channel)?;
+
+        // set GPIO interrupts, harmless when the INT pin isn't wired up
+        JoyFeatherwing::set_gpio_interupts(channel)?;
 
-        // set GPIO interrupts
-        //_ = JoyFeatherwing::set_GPIO_interupts().unwrap();
+        Ok(())
     }
 
-    pub fn measure_joy_buttons() {
-        let mut channel = I2c::new().unwrap();
-        _ = channel.set_slave_address(JOY_I2C_ADDR);
+    /// Sets the featherwing up again after a failed read, in case it was
+    /// reset. Its buttons read as let go until the next good read
+    fn recover<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
+        HELD_PINS.store(0, Ordering::Relaxed);
+        JoyFeatherwing::init(channel)
+    }
 
+    /// A failed read sets the featherwing up again, and only errors if that
+    /// fails too
+    pub fn measure_joy_buttons<I2C: I2cBus>(channel: &mut I2C) -> Result<(), InputError> {
         // digital read on button GPIO pins
-        if let Err(_) = channel.write(&[BaseRegister::GPIO as u8, GPIOFunctionRegister::GPIO as u8])
+        if channel
+            .write(
+                JOY_I2C_ADDR,
+                &[BaseRegister::GPIO as u8, GPIOFunctionRegister::GPIO as u8],
+            )
+            .is_err()
         {
-            JoyFeatherwing::init();
-            return; // abort silently
+            return JoyFeatherwing::recover(channel);
         }
         sleep(Duration::from_millis(DELAY_MS));
 
         let mut buf: [u8; 4] = [0x0; 4];
-        match channel.read(&mut buf) {
-            Ok(result_num) => {
-                if result_num != 4 {
-                    return; // abort silently
-                }
-            }
+        match channel.read(JOY_I2C_ADDR, &mut buf) {
+            Ok(_) => {}
             Err(_) => {
-                JoyFeatherwing::init();
-                return; // abort silently
+                return JoyFeatherwing::recover(channel);
             }
         }
-        let buf32 = u8s_to_u32(&buf)[0];
+        let buf32 = u32::from_be_bytes(buf);
 
-        let res = !(JOY_BUTTON_PIN_BITMASK[0] & buf32);
+        // buttons pull their pins low
+        HELD_PINS.store(!buf32 & BUTTON_MAP.get_pin_bitmask(), Ordering::Relaxed);
 
-        if (res & (1u32 << JoyInternalGPIOPins::ButtonA as u32)) != 0 {
-            BUTTON_A_PRESSED.store(true, Ordering::Relaxed);
-        } else {
-            BUTTON_A_PRESSED.store(false, Ordering::Relaxed);
-        }
+        Ok(())
+    }
 
-        if (res & (1u32 << JoyInternalGPIOPins::ButtonB as u32)) != 0 {
-            BUTTON_B_PRESSED.store(true, Ordering::Relaxed);
-        } else {
-            BUTTON_B_PRESSED.store(false, Ordering::Relaxed);
+    /// Raw reading of one ADC channel, from 0 to 1023
+    fn read_adc<I2C: I2cBus>(
+        channel: &mut I2C,
+        adc_channel: JoyADCChannels,
+    ) -> Result<u16, InputError> {
+        match channel.write(
+            JOY_I2C_ADDR,
+            &[
+                BaseRegister::ADC as u8,
+                ADCFunctionRegister::CHANNELOFFSET as u8 + adc_channel as u8,
+            ],
+        ) {
+            Ok(_) => {}
+            Err(_) => return Err(InputError::WriteErr),
         }
+        sleep(Duration::from_millis(DELAY_MS));
 
-        if (res & (1u32 << JoyInternalGPIOPins::ButtonX as u32)) != 0 {
-            BUTTON_X_PRESSED.store(true, Ordering::Relaxed);
-        } else {
-            BUTTON_X_PRESSED.store(false, Ordering::Relaxed);
+        let mut buf: [u8; 2] = [0x0; 2];
+        match channel.read(JOY_I2C_ADDR, &mut buf) {
+            Ok(_) => {}
+            Err(_) => return Err(InputError::ReadErr),
         }
 
-        if (res & (1u32 << JoyInternalGPIOPins::ButtonY as u32)) != 0 {
-            BUTTON_Y_PRESSED.store(true, Ordering::Relaxed);
-        } else {
-            BUTTON_Y_PRESSED.store(false, Ordering::Relaxed);
-        }
+        Ok(u16::from_be_bytes(buf))
+    }
 
-        if (res & (1u32 << JoyInternalGPIOPins::ButtonSelect as u32)) != 0 {
-            BUTTON_SELECT_PRESSED.store(true, Ordering::Relaxed);
-        } else {
-            BUTTON_SELECT_PRESSED.store(false, Ordering::Relaxed);
+    /// Reads both thumbstick axes, keeping the last position if either fails
+    pub fn measure_stick<I2C: I2cBus>(channel: &mut I2C, joystick: &Joystick) {
+        let raw_x = JoyFeatherwing::read_adc(channel, JoyADCChannels::StickX);
+        let raw_y = JoyFeatherwing::read_adc(channel, JoyADCChannels::StickY);
+        if let (Ok(x), Ok(y)) = (raw_x, raw_y) {
+            let position = BUTTON_MAP.rotate_position(joystick.get_position(x, y));
+            *STICK_POSITION.lock().unwrap() = position;
         }
     }
 
+    /// Thumbstick `x`, `y` from -1 to 1, positive is right and down on the
+    /// panel
+    pub fn get_stick() -> (f32, f32) {
+        *STICK_POSITION.lock().unwrap()
+    }
+
+    /// Bitmask of the seesaw pins held, whatever they are mapped to
+    pub fn get_held_pins() -> u32 {
+        HELD_PINS.load(Ordering::Relaxed)
+    }
+
+    /// Held buttons, mapped and turned by `input.buttons` and
+    /// `input.rotation`
     pub fn get_joy_buttons() -> Vec<Button> {
-        let mut buttons: Vec<Button> = Vec::new();
+        BUTTON_MAP.get_buttons(JoyFeatherwing::get_held_pins())
+    }
+}
 
-        if BUTTON_A_PRESSED.load(Ordering::Relaxed) {
-            buttons.push(Button::Up);
-        }
-        if BUTTON_B_PRESSED.load(Ordering::Relaxed) {
-            buttons.push(Button::Left);
-        }
-        if BUTTON_X_PRESSED.load(Ordering::Relaxed) {
-            buttons.push(Button::Down);
-        }
-        if BUTTON_Y_PRESSED.load(Ordering::Relaxed) {
-            buttons.push(Button::Right);
-        }
-        if BUTTON_SELECT_PRESSED.load(Ordering::Relaxed) {
-            buttons.push(Button::Select);
-        }
+impl InputSource for JoyFeatherwing {
+    fn poll(&mut self, _elapsed: Duration) -> Vec<ButtonEvent> {
+        std::mem::take(&mut *EVENTS.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::mock_i2c::MockBus;
+
+    /// Held for tests that read the buttons, which all share `HELD_PINS`
+    static HELD_PINS_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn init_sets_up_the_button_pins() {
+        let bus = MockBus::new().with_seesaw();
+
+        JoyFeatherwing::init(&mut bus.clone()).unwrap();
+
+        let seesaw = bus.seesaw();
+        let mask = BUTTON_MAP.get_pin_bitmask();
+        assert_eq!(seesaw.resets, 1);
+        assert_eq!(seesaw.inputs, mask);
+        assert_eq!(seesaw.pulls & seesaw.set, mask);
+        assert_eq!(seesaw.interrupts, mask);
+    }
+
+    #[test]
+    fn reset_is_retried_until_written() {
+        let bus = MockBus::new().with_seesaw();
+        bus.fail_writes(3);
+
+        JoyFeatherwing::init(&mut bus.clone()).unwrap();
+
+        assert_eq!(bus.seesaw().resets, 1);
+    }
+
+    #[test]
+    fn failed_reads_reinitialise() {
+        let _lock = HELD_PINS_LOCK.lock().unwrap();
+        let bus = MockBus::new().with_seesaw();
+        JoyFeatherwing::init(&mut bus.clone()).unwrap();
+        bus.set_held_pins(1 << 6);
+        JoyFeatherwing::measure_joy_buttons(&mut bus.clone()).unwrap();
+        assert_eq!(JoyFeatherwing::get_held_pins(), 1 << 6);
+
+        // held buttons are let go rather than repeating through the reset
+        bus.fail_reads(1);
+        JoyFeatherwing::measure_joy_buttons(&mut bus.clone()).unwrap();
+        assert_eq!(bus.seesaw().resets, 2);
+        assert_eq!(JoyFeatherwing::get_held_pins(), 0);
+
+        // the featherwing has gone, the reset is given up on
+        bus.fail_writes(100);
+        assert!(matches!(
+            JoyFeatherwing::measure_joy_buttons(&mut bus.clone()),
+            Err(InputError::WriteErr)
+        ));
+        assert_eq!(bus.seesaw().resets, 2);
+    }
+
+    #[test]
+    fn held_buttons_and_the_stick_are_read() {
+        let _lock = HELD_PINS_LOCK.lock().unwrap();
+        let bus = MockBus::new().with_seesaw();
+        JoyFeatherwing::init(&mut bus.clone()).unwrap();
+
+        // A and Select
+        bus.set_held_pins(1 << 6 | 1 << 14);
+        JoyFeatherwing::measure_joy_buttons(&mut bus.clone()).unwrap();
+        assert_eq!(
+            JoyFeatherwing::get_joy_buttons(),
+            vec![Button::Up, Button::Select]
+        );
+
+        // the stick pushed all the way right
+        bus.set_adc(JoyADCChannels::StickX as usize, 1023);
+        bus.set_adc(JoyADCChannels::StickY as usize, 512);
+        let joystick = Joystick::new(&config::get().input.joystick);
+        JoyFeatherwing::measure_stick(&mut bus.clone(), &joystick);
+        assert_eq!(JoyFeatherwing::get_stick(), (1.0, 0.0));
+    }
+
+    #[test]
+    fn probe_finds_the_seesaw() {
+        assert!(matches!(
+            JoyFeatherwing::probe(&mut MockBus::new().with_seesaw()),
+            Ok(HardwareID::SAMD09)
+        ));
+        assert!(matches!(
+            JoyFeatherwing::probe(&mut MockBus::new()),
+            Err(InputError::HwNotFound)
+        ));
+        // something else answering at the address
+        let bus = MockBus::new().with_seesaw();
+        bus.set_hardware_id(0x12);
+        assert!(matches!(
+            JoyFeatherwing::probe(&mut bus.clone()),
+            Err(InputError::HwNotFound)
+        ));
+    }
+
+    #[test]
+    fn interrupts_are_cleared() {
+        let bus = MockBus::new().with_seesaw();
+        JoyFeatherwing::init(&mut bus.clone()).unwrap();
 
-        buttons
+        bus.set_held_pins(1 << 7);
+        assert_eq!(bus.seesaw().interrupt_flags, 1 << 7);
+        JoyFeatherwing::clear_gpio_interrupts(&mut bus.clone()).unwrap();
+        assert_eq!(bus.seesaw().interrupt_flags, 0);
     }
 }
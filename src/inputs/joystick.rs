@@ -0,0 +1,118 @@
+use super::Button;
+use crate::config::JoystickConfig;
+
+/// Largest reading of the seesaw's 10 bit ADC
+pub const ADC_MAX: u16 = 1023;
+
+/// Turns raw thumbstick readings into positions and direction buttons
+pub struct Joystick {
+    center_x: u16,
+    center_y: u16,
+    dead_zone: u16,
+    range: u16,
+    invert_x: bool,
+    invert_y: bool,
+    button_threshold: f32,
+}
+
+impl Joystick {
+    pub fn new(config: &JoystickConfig) -> Self {
+        Self {
+            center_x: config.center_x,
+            center_y: config.center_y,
+            dead_zone: config.dead_zone,
+            range: config.range,
+            invert_x: config.invert_x,
+            invert_y: config.invert_y,
+            button_threshold: config.button_threshold as f32,
+        }
+    }
+
+    /// -1 to 1 along one axis, 0 inside the dead zone
+    fn get_axis(&self, raw: u16, center: u16, invert: bool) -> f32 {
+        let offset = raw as i32 - center as i32;
+        let distance = offset.unsigned_abs() as u16;
+        if distance <= self.dead_zone {
+            return 0.0;
+        }
+
+        // scaled from the edge of the dead zone, so there is no jump past it
+        let value = ((distance - self.dead_zone) as f32 / (self.range - self.dead_zone) as f32)
+            .min(1.0)
+            * offset.signum() as f32;
+
+        match invert {
+            true => -value,
+            false => value,
+        }
+    }
+
+    /// `x`, `y` from -1 to 1, positive is right and down like the panel
+    pub fn get_position(&self, raw_x: u16, raw_y: u16) -> (f32, f32) {
+        (
+            self.get_axis(raw_x, self.center_x, self.invert_x),
+            self.get_axis(raw_y, self.center_y, self.invert_y),
+        )
+    }
+
+    /// Directions the stick is pushed far enough in to count as held
+    /// buttons, both of them on a diagonal
+    pub fn get_buttons(&self, (x, y): (f32, f32)) -> Vec<Button> {
+        let mut buttons = Vec::new();
+
+        if x <= -self.button_threshold {
+            buttons.push(Button::Left);
+        }
+        if x >= self.button_threshold {
+            buttons.push(Button::Right);
+        }
+        if y <= -self.button_threshold {
+            buttons.push(Button::Up);
+        }
+        if y >= self.button_threshold {
+            buttons.push(Button::Down);
+        }
+
+        buttons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joystick() -> Joystick {
+        Joystick::new(&JoystickConfig {
+            center_x: 500,
+            center_y: 520,
+            dead_zone: 20,
+            range: 420,
+            invert_x: false,
+            invert_y: true,
+            ..JoystickConfig::default()
+        })
+    }
+
+    #[test]
+    fn readings_are_calibrated() {
+        let joystick = joystick();
+
+        // a stick that doesn't quite centre stays put
+        assert_eq!(joystick.get_position(515, 505), (0.0, 0.0));
+        assert_eq!(joystick.get_position(720, 520), (0.5, 0.0));
+        // past the range is clamped, and y is inverted
+        assert_eq!(joystick.get_position(0, ADC_MAX), (-1.0, -1.0));
+    }
+
+    #[test]
+    fn pushing_far_enough_holds_a_direction() {
+        let joystick = joystick();
+
+        assert_eq!(joystick.get_buttons((0.3, -0.2)), vec![]);
+        assert_eq!(joystick.get_buttons((0.0, 0.9)), vec![Button::Down]);
+        assert_eq!(
+            joystick.get_buttons((-0.7, -0.7)),
+            vec![Button::Left, Button::Up]
+        );
+    }
+}
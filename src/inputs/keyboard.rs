@@ -0,0 +1,60 @@
+use std::io::stdin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use super::{Button, ButtonEvent, InputSource};
+
+/// Typed letters for `KeyboardSource::stdin`
+fn get_button(letter: char) -> Option<Button> {
+    match letter.to_ascii_lowercase() {
+        'w' => Some(Button::Up),
+        's' => Some(Button::Down),
+        'a' => Some(Button::Left),
+        'd' => Some(Button::Right),
+        'e' => Some(Button::Select),
+        _ => None,
+    }
+}
+
+/// Keys read by a display backend, eg. the simulator window or the
+/// terminal, which sends them over a channel as they arrive
+pub struct KeyboardSource {
+    events: Receiver<ButtonEvent>,
+}
+
+impl KeyboardSource {
+    /// The sender goes to whatever reads the keys
+    pub fn channel() -> (Sender<ButtonEvent>, Self) {
+        let (sender, events) = channel();
+        (sender, Self { events })
+    }
+
+    /// Buttons typed on stdin, for when there are neither featherwing
+    /// buttons nor a backend keyboard, eg. the hat over SSH. Lines are read
+    /// whole, so each ends with Enter: `w`, `a`, `s` and `d` move and `e` or
+    /// an empty line selects
+    pub fn stdin() -> Self {
+        let (sender, keyboard) = KeyboardSource::channel();
+        std::thread::spawn(move || {
+            for line in stdin().lines() {
+                let buttons: Vec<Button> = match line {
+                    Ok(x) if x.trim().is_empty() => vec![Button::Select],
+                    Ok(x) => x.chars().filter_map(get_button).collect(),
+                    Err(_) => return,
+                };
+                for button in buttons {
+                    if sender.send(ButtonEvent::pressed(button)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        keyboard
+    }
+}
+
+impl InputSource for KeyboardSource {
+    fn poll(&mut self, _elapsed: Duration) -> Vec<ButtonEvent> {
+        self.events.try_iter().collect()
+    }
+}
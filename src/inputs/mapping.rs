@@ -0,0 +1,98 @@
+use super::Button;
+use crate::config::{ButtonMapping, InputConfig};
+
+/// Directions in clockwise order
+const DIRECTIONS: [Button; 4] = [Button::Up, Button::Right, Button::Down, Button::Left];
+
+/// Turns the seesaw pins that are held into buttons, following
+/// `input.buttons` and `input.rotation`
+pub struct ButtonMap {
+    buttons: Vec<ButtonMapping>,
+    /// quarter turns clockwise
+    quarter_turns: usize,
+}
+
+impl ButtonMap {
+    pub fn new(config: &InputConfig) -> Self {
+        Self {
+            buttons: config.buttons.clone(),
+            quarter_turns: (config.rotation / 90) as usize % 4,
+        }
+    }
+
+    /// Every mapped pin, eg. to set them up as inputs
+    pub fn get_pin_bitmask(&self) -> u32 {
+        self.buttons.iter().fold(0, |mask, x| mask | 1 << x.pin)
+    }
+
+    /// Direction on the panel that `button` points to on the featherwing.
+    /// Select stays select however the featherwing is turned
+    pub fn rotate(&self, button: Button) -> Button {
+        match DIRECTIONS.iter().position(|x| *x == button) {
+            Some(i) => DIRECTIONS[(i + self.quarter_turns) % 4],
+            None => button,
+        }
+    }
+
+    /// `rotate` for a thumbstick position, `y` is positive going down
+    pub fn rotate_position(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        match self.quarter_turns {
+            1 => (-y, x),
+            2 => (-x, -y),
+            3 => (y, -x),
+            _ => (x, y),
+        }
+    }
+
+    /// Buttons held according to a bitmask of held pins
+    pub fn get_buttons(&self, held_pins: u32) -> Vec<Button> {
+        self.buttons
+            .iter()
+            .filter(|x| held_pins & 1 << x.pin != 0)
+            .map(|x| self.rotate(x.action))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_map(rotation: u16) -> ButtonMap {
+        ButtonMap::new(&InputConfig {
+            rotation,
+            ..InputConfig::default()
+        })
+    }
+
+    #[test]
+    fn pins_map_to_their_actions() {
+        let map = get_map(0);
+        assert_eq!(
+            map.get_pin_bitmask(),
+            1 << 6 | 1 << 7 | 1 << 9 | 1 << 10 | 1 << 14
+        );
+
+        assert_eq!(map.get_buttons(0), vec![]);
+        assert_eq!(
+            map.get_buttons(1 << 6 | 1 << 14),
+            vec![Button::Up, Button::Select]
+        );
+        // unmapped pins are ignored
+        assert_eq!(map.get_buttons(1 << 7 | 1 << 3), vec![Button::Left]);
+    }
+
+    #[test]
+    fn rotation_turns_directions_clockwise() {
+        let map = get_map(90);
+        assert_eq!(map.rotate(Button::Up), Button::Right);
+        assert_eq!(map.rotate(Button::Left), Button::Up);
+        assert_eq!(map.rotate(Button::Select), Button::Select);
+        assert_eq!(map.rotate_position((0.0, -1.0)), (1.0, 0.0));
+
+        let map = get_map(270);
+        assert_eq!(map.rotate(Button::Up), Button::Left);
+        assert_eq!(map.rotate_position((0.0, -1.0)), (-1.0, 0.0));
+        assert_eq!(get_map(180).rotate(Button::Down), Button::Up);
+    }
+}
@@ -0,0 +1,296 @@
+//! An in-memory I2C bus with a simulated BH1750 and seesaw on it, so the
+//! drivers can be tested off the Pi. Failures can be injected to exercise
+//! the error and retry paths.
+
+use std::sync::{Arc, Mutex};
+
+use embedded_hal::blocking::i2c::{Read, Write};
+
+const BH1750_ADDR: u8 = 0x23;
+const SEESAW_ADDR: u8 = 0x49;
+
+// seesaw base and function registers
+const STATUS_BASE: u8 = 0x00;
+const GPIO_BASE: u8 = 0x01;
+const ADC_BASE: u8 = 0x09;
+const STATUS_HWID: u8 = 0x01;
+const STATUS_SWRST: u8 = 0x7f;
+const GPIO_DIRCLR: u8 = 0x03;
+const GPIO_GPIO: u8 = 0x04;
+const GPIO_SET: u8 = 0x05;
+const GPIO_INTENSET: u8 = 0x08;
+const GPIO_INTFLAG: u8 = 0x0a;
+const GPIO_PULLENSET: u8 = 0x0b;
+const ADC_CHANNEL_OFFSET: u8 = 0x07;
+
+#[derive(Debug, PartialEq)]
+pub enum MockError {
+    /// nothing answered at the address
+    Nack,
+    /// from `fail_writes` or `fail_reads`
+    Injected,
+}
+
+#[derive(Clone, Debug)]
+pub struct MockBh1750 {
+    /// every opcode written, in order
+    pub opcodes: Vec<u8>,
+    /// light falling on the sensor
+    pub lux: f32,
+    pub mtreg: u8,
+    /// last measurement opcode, none until one is sent
+    pub mode: Option<u8>,
+}
+
+impl MockBh1750 {
+    fn new(lux: f32) -> Self {
+        Self {
+            opcodes: Vec::new(),
+            lux,
+            mtreg: 69,
+            mode: None,
+        }
+    }
+
+    /// Whether the last measurement was continuous
+    pub fn is_continuous(&self) -> bool {
+        matches!(self.mode, Some(0x10..=0x13))
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.opcodes.extend(bytes);
+        for opcode in bytes {
+            match opcode {
+                0x10 | 0x11 | 0x13 | 0x20 | 0x21 | 0x23 => self.mode = Some(*opcode),
+                0x40..=0x47 => self.mtreg = (opcode & 0x7) << 5 | self.mtreg & 0x1f,
+                0x60..=0x7f => self.mtreg = self.mtreg & 0xe0 | opcode & 0x1f,
+                _ => {}
+            }
+        }
+    }
+
+    /// Counts for the light level, in the last mode and MTreg
+    fn get_counts(&self) -> u16 {
+        let per_lux = match self.mode {
+            None => return 0,
+            Some(0x11) | Some(0x21) => 2.4,
+            Some(_) => 1.2,
+        };
+        let counts = self.lux * per_lux * self.mtreg as f32 / 69.0;
+        counts.round().min(u16::MAX as f32) as u16
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct MockSeesaw {
+    pub hardware_id: u8,
+    pub resets: usize,
+    /// pins set to inputs with DIRCLR
+    pub inputs: u32,
+    /// pins with their pull resistor enabled, pulling up once SET
+    pub pulls: u32,
+    pub set: u32,
+    pub interrupts: u32,
+    pub interrupt_flags: u32,
+    /// pins a button is pulling low
+    pub held: u32,
+    pub adc: [u16; 2],
+    /// base and function register of the last write, what reads return
+    selected: (u8, u8),
+}
+
+impl MockSeesaw {
+    /// Pins read high when pulled up and not held, floating pins read low
+    fn get_levels(&self) -> u32 {
+        self.pulls & self.set & !self.held
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let (base, function) = match bytes {
+            [base, function, ..] => (*base, *function),
+            _ => return,
+        };
+        self.selected = (base, function);
+
+        let mask = match bytes[2..].try_into() {
+            Ok(x) => u32::from_be_bytes(x),
+            Err(_) => 0,
+        };
+        match (base, function) {
+            (STATUS_BASE, STATUS_SWRST) => {
+                *self = MockSeesaw {
+                    hardware_id: self.hardware_id,
+                    resets: self.resets + 1,
+                    held: self.held,
+                    adc: self.adc,
+                    ..MockSeesaw::default()
+                }
+            }
+            (GPIO_BASE, GPIO_DIRCLR) => self.inputs |= mask,
+            (GPIO_BASE, GPIO_PULLENSET) => self.pulls |= mask,
+            (GPIO_BASE, GPIO_SET) => self.set |= mask,
+            (GPIO_BASE, GPIO_INTENSET) => self.interrupts |= mask,
+            _ => {}
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        let value: Vec<u8> = match self.selected {
+            (STATUS_BASE, STATUS_HWID) => vec![self.hardware_id],
+            (GPIO_BASE, GPIO_GPIO) => self.get_levels().to_be_bytes().to_vec(),
+            (GPIO_BASE, GPIO_INTFLAG) => {
+                let flags = self.interrupt_flags;
+                self.interrupt_flags = 0;
+                flags.to_be_bytes().to_vec()
+            }
+            (ADC_BASE, x) if x >= ADC_CHANNEL_OFFSET => {
+                let channel = (x - ADC_CHANNEL_OFFSET) as usize;
+                self.adc
+                    .get(channel)
+                    .copied()
+                    .unwrap_or(0)
+                    .to_be_bytes()
+                    .to_vec()
+            }
+            _ => Vec::new(),
+        };
+
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = value.get(i).copied().unwrap_or(0);
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    bh1750: Option<MockBh1750>,
+    seesaw: Option<MockSeesaw>,
+    /// writes left to fail
+    failing_writes: usize,
+    /// reads left to fail
+    failing_reads: usize,
+}
+
+/// Clones share the same devices, so a test can keep one to inspect and
+/// change them while a driver owns another
+#[derive(Clone, Default)]
+pub struct MockBus {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockBus {
+    /// A bus with nothing on it, every transfer is NACKed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A light sensor with `lux` falling on it
+    pub fn with_bh1750(self, lux: f32) -> Self {
+        self.state.lock().unwrap().bh1750 = Some(MockBh1750::new(lux));
+        self
+    }
+
+    /// A seesaw identifying as a SAMD09, like the joy featherwing's
+    pub fn with_seesaw(self) -> Self {
+        self.state.lock().unwrap().seesaw = Some(MockSeesaw {
+            hardware_id: 0x55,
+            ..MockSeesaw::default()
+        });
+        self
+    }
+
+    /// Makes the next `count` writes fail
+    pub fn fail_writes(&self, count: usize) {
+        self.state.lock().unwrap().failing_writes = count;
+    }
+
+    /// Makes the next `count` reads fail
+    pub fn fail_reads(&self, count: usize) {
+        self.state.lock().unwrap().failing_reads = count;
+    }
+
+    pub fn bh1750(&self) -> MockBh1750 {
+        self.state.lock().unwrap().bh1750.clone().unwrap()
+    }
+
+    pub fn seesaw(&self) -> MockSeesaw {
+        self.state.lock().unwrap().seesaw.clone().unwrap()
+    }
+
+    pub fn set_lux(&self, lux: f32) {
+        self.state.lock().unwrap().bh1750.as_mut().unwrap().lux = lux;
+    }
+
+    /// Holds the buttons on `pins` down and releases the rest, flagging an
+    /// interrupt for every pin that changes
+    pub fn set_held_pins(&self, pins: u32) {
+        let mut state = self.state.lock().unwrap();
+        let seesaw = state.seesaw.as_mut().unwrap();
+        seesaw.interrupt_flags |= (seesaw.held ^ pins) & seesaw.interrupts;
+        seesaw.held = pins;
+    }
+
+    pub fn set_hardware_id(&self, id: u8) {
+        self.state
+            .lock()
+            .unwrap()
+            .seesaw
+            .as_mut()
+            .unwrap()
+            .hardware_id = id;
+    }
+
+    pub fn set_adc(&self, channel: usize, value: u16) {
+        self.state.lock().unwrap().seesaw.as_mut().unwrap().adc[channel] = value;
+    }
+}
+
+impl Write for MockBus {
+    type Error = MockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if state.failing_writes > 0 {
+            state.failing_writes -= 1;
+            return Err(MockError::Injected);
+        }
+
+        match (address, &mut state.bh1750, &mut state.seesaw) {
+            (BH1750_ADDR, Some(x), _) => x.write(bytes),
+            (SEESAW_ADDR, _, Some(x)) => x.write(bytes),
+            _ => return Err(MockError::Nack),
+        }
+        Ok(())
+    }
+}
+
+impl Read for MockBus {
+    type Error = MockError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), MockError> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if state.failing_reads > 0 {
+            state.failing_reads -= 1;
+            return Err(MockError::Injected);
+        }
+
+        match (address, &mut state.bh1750, &mut state.seesaw) {
+            (BH1750_ADDR, Some(x), _) => {
+                for (i, byte) in x
+                    .get_counts()
+                    .to_be_bytes()
+                    .iter()
+                    .enumerate()
+                    .take(buffer.len())
+                {
+                    buffer[i] = *byte;
+                }
+            }
+            (SEESAW_ADDR, _, Some(x)) => x.read(buffer),
+            _ => return Err(MockError::Nack),
+        }
+        Ok(())
+    }
+}
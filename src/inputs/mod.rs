@@ -1,22 +1,66 @@
+use std::time::Duration;
+
+use embedded_hal::blocking::i2c::{Read, Write};
+use rppal::i2c::I2c;
+use serde::Deserialize;
+
+pub use events::{ButtonEvent, EventKind};
+
 pub mod bh1750;
+pub mod events;
+pub mod health;
 pub mod joy_featherwing;
+pub mod joystick;
+pub mod keyboard;
+pub mod mapping;
+#[cfg(test)]
+pub mod mock_i2c;
+pub mod scripted;
+
+/// A button on whichever input device is in use, named after what it does
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+}
+
+impl Button {
+    pub const ALL: [Button; 5] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::Select,
+    ];
+}
+
+/// Anything that can press buttons, eg. the joy featherwing, a keyboard or
+/// a script
+pub trait InputSource {
+    /// Button events since the last poll, `elapsed` is the update time that
+    /// has passed since then
+    fn poll(&mut self, elapsed: Duration) -> Vec<ButtonEvent>;
+}
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum InputError {
     ReadErr,
     WriteErr,
     HwNotFound,
 }
 
-fn u32_to_u8s(arr: &[u32]) -> &[u8] {
-    let len = 4 * arr.len();
-    let ptr = arr.as_ptr() as *const u8;
-    unsafe { std::slice::from_raw_parts(ptr, len) }
-}
+/// An I2C bus the sensor drivers talk to their devices over, the Pi's
+/// through rppal, or `MockBus` in tests
+pub trait I2cBus: Read + Write {}
+
+impl<T: Read + Write> I2cBus for T {}
 
-pub fn u8s_to_u32(arr: &[u8]) -> &[u32] {
-    let len = arr.len() / 4;
-    let ptr = arr.as_ptr() as *const u32;
-    unsafe { std::slice::from_raw_parts(ptr, len) }
+/// The Pi's I2C bus, `HwNotFound` when there isn't one, eg. running the
+/// simulator on a desktop
+pub fn open_bus() -> Result<I2c, InputError> {
+    I2c::new().map_err(|_| InputError::HwNotFound)
 }
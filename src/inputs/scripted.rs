@@ -0,0 +1,120 @@
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Button, ButtonEvent, EventKind, InputSource};
+
+/// One button event in an input script
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptEvent {
+    /// milliseconds after the script starts
+    pub at_ms: u64,
+    pub button: Button,
+    /// `pressed` when left out
+    #[serde(default)]
+    pub kind: EventKind,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    events: Vec<ScriptEvent>,
+}
+
+/// Replays a timed list of presses, eg. to demo or test the UI without
+/// anyone at the buttons. See `tests/fixtures/inputs.toml` for the format
+pub struct ScriptedSource {
+    /// ordered by `at_ms`
+    events: Vec<ScriptEvent>,
+    /// index of the first event that hasn't been pressed yet
+    next: usize,
+    elapsed: Duration,
+}
+
+impl ScriptedSource {
+    pub fn new(mut events: Vec<ScriptEvent>) -> Self {
+        // stable, so presses at the same time keep their order
+        events.sort_by_key(|x| x.at_ms);
+
+        Self {
+            events,
+            next: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let script: Script = toml::from_str(&contents)
+            .map_err(|e| format!("invalid input script {}: {}", path.display(), e))?;
+
+        Ok(Self::new(script.events))
+    }
+}
+
+impl InputSource for ScriptedSource {
+    fn poll(&mut self, elapsed: Duration) -> Vec<ButtonEvent> {
+        self.elapsed += elapsed;
+
+        let mut events = Vec::new();
+        while let Some(event) = self.events.get(self.next) {
+            if Duration::from_millis(event.at_ms) > self.elapsed {
+                break;
+            }
+            events.push(ButtonEvent {
+                button: event.button,
+                kind: event.kind,
+            });
+            self.next += 1;
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::events::presses;
+
+    #[test]
+    fn presses_are_replayed_on_time() {
+        let mut source = ScriptedSource::new(vec![
+            ScriptEvent {
+                at_ms: 250,
+                button: Button::Select,
+                kind: EventKind::Pressed,
+            },
+            ScriptEvent {
+                at_ms: 100,
+                button: Button::Down,
+                kind: EventKind::Pressed,
+            },
+            ScriptEvent {
+                at_ms: 100,
+                button: Button::Right,
+                kind: EventKind::Pressed,
+            },
+        ]);
+        let step = Duration::from_millis(100);
+
+        assert_eq!(source.poll(Duration::ZERO), vec![]);
+        assert_eq!(source.poll(step), presses(&[Button::Down, Button::Right]));
+        assert_eq!(source.poll(step), vec![]);
+        assert_eq!(source.poll(step), presses(&[Button::Select]));
+        assert_eq!(source.poll(step), vec![]);
+    }
+
+    #[test]
+    fn example_script_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/inputs.toml");
+        let mut source = ScriptedSource::load(&path).unwrap();
+
+        let events = source.poll(Duration::from_secs(60));
+        assert_eq!(events.len(), 6);
+        assert_eq!(events.last(), Some(&ButtonEvent::pressed(Button::Left)));
+    }
+}
@@ -1,55 +1,220 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod brightness;
+mod carousel;
+mod config;
 mod inputs;
 mod matrix;
-mod state;
+mod scheduler;
+mod screen;
+#[cfg(test)]
+mod snapshots;
 mod states;
+mod transition;
+mod weather;
 
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use brightness::BrightnessControl;
+use carousel::Carousel;
+use config::Config;
 use inputs::bh1750::BH1750;
-use inputs::joy_featherwing::Button;
+use inputs::health::{get_health, Device, Health};
 use inputs::joy_featherwing::JoyFeatherwing;
+use inputs::keyboard::KeyboardSource;
+use inputs::scripted::ScriptedSource;
+use inputs::{ButtonEvent, InputSource};
+use matrix::BackendKind;
 use matrix::Matrix;
-use state::State;
-use states::region_select::region_select_state;
-use states::time::time_state;
+use scheduler::{Interval, Scheduler};
+use screen::ScreenRegistry;
+use states::buttons::ButtonTestScreen;
+use states::date::DateScreen;
+use states::persist::SavedRegion;
+use states::region_select::RegionSelectScreen;
+use states::time::TimeScreen;
+use states::weather::WeatherScreen;
+use states::SelectedTimezone;
+
+/// Value following a `--name value` command line flag
+fn get_arg(name: &str) -> Option<String> {
+    std::env::args().skip_while(|x| x != name).nth(1)
+}
 
 pub fn main() {
-    let brightness_update_interval: u8 = 10;
-    let mut brightness_frames_since_last_update: u8 = 0;
+    // `--config <file>` replaces /etc/smart-clock/config.toml
+    let config_path = get_arg("--config").map(PathBuf::from);
+    let config = match Config::load(config_path.as_deref()) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    config::init(config.clone());
+
+    let brightness_period = Duration::from_millis(config.brightness.update_interval_ms);
+    let mut brightness_interval = Interval::new(brightness_period);
+    let mut brightness = BrightnessControl::new(&config.brightness, config.matrix.brightness);
+    let mut input_interval = Interval::new(Duration::from_millis(config.input.poll_interval_ms));
+    // update time since the input sources were last polled
+    let mut input_elapsed = Duration::ZERO;
+
+    // display backend can be overridden with `--backend <hat|simulator|terminal|headless>`
+    let backend = match get_arg("--backend").or(config.display.backend) {
+        Some(x) => match x.parse() {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => BackendKind::default(),
+    };
+    let mut matrix = match Matrix::new(&config.matrix, backend) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // `--snapshot <file.png|file.rgb>` keeps the latest frame on disk,
+    // `--frames <n>` exits after n frames
+    let snapshot_path = get_arg("--snapshot").map(PathBuf::from);
+    let frame_limit: Option<usize> = match get_arg("--frames").map(|x| x.parse()) {
+        Some(Ok(x)) if x > 0 => Some(x),
+        Some(_) => {
+            eprintln!("--frames must be a number of frames greater than 0");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    let mut frame_count: usize = 0;
+
+    // skip region select if a region was saved before the last restart
+    let timezone = SelectedTimezone::new();
+    let mut region_select = RegionSelectScreen::new(timezone.clone(), &config.storage.state_file);
+    let first_screen = match SavedRegion::load(&config.storage.state_file) {
+        Some(x) if region_select.restore(&x) => TimeScreen::NAME,
+        _ => RegionSelectScreen::NAME,
+    };
+
+    let mut screens = ScreenRegistry::new();
+    screens.register(Box::new(region_select));
+    screens.register(Box::new(TimeScreen::new(timezone.clone())));
+    screens.register(Box::new(DateScreen::new(timezone.clone())));
+    screens.register(Box::new(WeatherScreen::new(timezone.clone())));
+    screens.register(Box::new(ButtonTestScreen::new()));
+
+    // `--screen <name>` starts on another screen, eg. `buttons` to check the
+    // featherwing's button mapping
+    let first_screen = get_arg("--screen").unwrap_or(String::from(first_screen));
+    if let Err(e) = screens.switch_to(&first_screen) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = screens.set_transitions(&config.transitions) {
+        eprintln!("invalid config: {}", e);
+        std::process::exit(1);
+    }
 
-    let mut matrix = Matrix::new(None);
+    let mut carousel = match Carousel::new(&config.carousel.playlist, &screens) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("invalid config: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    //// initial state = RegionSelect
-    let mut current_state = State::RegionSelect;
+    // every source's presses are merged, so hardware, a developer at the
+    // keyboard and a script can all drive the same screens
+    let mut input_sources: Vec<Box<dyn InputSource>> = Vec::new();
+    match JoyFeatherwing::start() {
+        Ok(x) => input_sources.push(Box::new(x)),
+        Err(e) => eprintln!("joy featherwing unavailable: {:?}", e),
+    }
+    match matrix.take_keyboard() {
+        Some(x) => input_sources.push(Box::new(x)),
+        // nothing else to press buttons with, so read them from stdin
+        None if get_health(Device::JoyFeatherwing) != Health::Ok => {
+            eprintln!("reading buttons from stdin: w, a, s, d to move, Enter to select");
+            input_sources.push(Box::new(KeyboardSource::stdin()));
+        }
+        None => {}
+    }
+    // `--input-script <file>` replays presses from a file, see tests/fixtures/inputs.toml
+    if let Some(path) = get_arg("--input-script") {
+        match ScriptedSource::load(Path::new(&path)) {
+            Ok(x) => input_sources.push(Box::new(x)),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    JoyFeatherwing::init();
+    //// measure brightness on seperate thread, without a sensor the panel
+    //// stays at `matrix.brightness`
+    if let Err(e) = BH1750::start(&config.brightness) {
+        eprintln!(
+            "light sensor unavailable, brightness fixed at {}%: {:?}",
+            config.matrix.brightness, e
+        );
+    }
 
-    //// measure brightness on seperate thread
-    std::thread::spawn(move || loop {
-        _ = BH1750::measure_brightness();
-    });
+    // look up the weather on a seperate thread
+    weather::start_worker(timezone);
 
-    // measure button presses on seperate thread
-    std::thread::spawn(move || loop {
-        JoyFeatherwing::measure_joy_buttons();
-    });
+    let mut scheduler = Scheduler::new(config.display.fps);
 
     loop {
-        if brightness_update_interval == brightness_frames_since_last_update {
-            let brightness = BH1750::get_brightness();
-            matrix.set_brightness(brightness);
-            brightness_frames_since_last_update = 0;
+        // catch up on update steps, a slow frame runs several
+        for _ in 0..scheduler.wait() {
+            let dt = scheduler.get_step();
+
+            // a failing sensor's last reading is kept until it recovers, and
+            // the panel stays at its start-up brightness until the first one
+            if brightness_interval.tick(dt) && get_health(Device::LightSensor) == Health::Ok {
+                if let Some(lux) = BH1750::get_lux() {
+                    brightness.add_reading(lux, brightness_period);
+                }
+            }
+            if let Some(x) = brightness.update(dt) {
+                matrix.set_brightness(x);
+            }
+
+            let mut events: Vec<ButtonEvent> = Vec::new();
+            input_elapsed += dt;
+            if input_interval.tick(dt) {
+                for source in &mut input_sources {
+                    events.extend(source.poll(input_elapsed));
+                }
+                input_elapsed = Duration::ZERO;
+            }
+
+            // the carousel gets first pick of the buttons
+            let events = carousel.handle_input(&mut screens, &events);
+            screens.handle_input(&events);
+            carousel.update(&mut screens, dt);
+            screens.update(dt);
         }
-        brightness_frames_since_last_update += 1;
 
         matrix.pre_draw();
+        screens.draw(matrix.get_canvas());
+        matrix.post_draw();
 
-        current_state = match current_state {
-            State::RegionSelect => region_select_state(&mut matrix),
-            State::Time => time_state(&mut matrix),
-        };
+        if let Some(path) = &snapshot_path {
+            if let Err(e) = matrix.save_snapshot(path) {
+                eprintln!("failed to save snapshot to {}: {}", path.display(), e);
+            }
+        }
 
-        matrix = matrix.post_draw();
+        frame_count += 1;
+        if frame_limit == Some(frame_count) {
+            return;
+        }
     }
 }
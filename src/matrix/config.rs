@@ -0,0 +1,93 @@
+use embedded_graphics::prelude::Size;
+use serde::Deserialize;
+
+/// Panel geometry and rpi-rgb-led-matrix driver options.
+///
+/// Field names follow the `--led-*` flags of rpi-rgb-led-matrix, see
+/// https://github.com/hzeller/rpi-rgb-led-matrix#changing-parameters-via-command-line-flags
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatrixConfig {
+    /// columns of a single panel
+    pub cols: u32,
+    /// rows of a single panel
+    pub rows: u32,
+    /// panels daisy-chained on one output, extends the canvas horizontally
+    pub chain_length: u32,
+    /// parallel chains (1-3), extends the canvas vertically
+    pub parallel: u32,
+    /// GPIO mapping of the HAT, eg. `regular`, `adafruit-hat`, `adafruit-hat-pwm`
+    pub hardware_mapping: String,
+    /// order the panel expects the colour channels in, eg. `rgb`, `rbg`
+    pub led_rgb_sequence: String,
+    /// colour depth per channel (1-11), lower refreshes faster
+    pub pwm_bits: u8,
+    /// base time-unit of the on-time in the lowest significant bit
+    pub pwm_lsb_nanoseconds: u32,
+    /// time dithering of the lower bits
+    pub pwm_dither_bits: u32,
+    /// 0 = progressive, 1 = interlaced
+    pub scan_mode: u32,
+    /// 0 = default, 1 = AB-addressed panels, 2 = direct row select, 3 = ABC-addressed, 4 = ABC shift + DE direct
+    pub row_addr_type: u32,
+    /// multiplexing type, 0 = direct
+    pub multiplexing: u32,
+    /// driver chip initialisation, eg. `FM6126A`, empty for none
+    pub panel_type: String,
+    /// semicolon-separated pixel mappers, eg. `U-mapper;Rotate:90`
+    pub pixel_mapper_config: String,
+    /// use the hardware pin-pulse generator, breaks when sound is enabled
+    pub hardware_pulsing: bool,
+    /// for panels that switch colours on when they should be off
+    pub inverse_colors: bool,
+    /// upper limit of the refresh rate in Hz, 0 = unlimited
+    pub limit_refresh: u32,
+    /// print the refresh rate to the terminal
+    pub show_refresh_rate: bool,
+    /// start-up brightness as a percentage
+    pub brightness: u8,
+    /// slow down GPIO writes for faster Pis (0-4)
+    pub gpio_slowdown: u32,
+    /// fork into the background once the matrix is initialised
+    pub daemon: bool,
+    /// drop root privileges after the GPIOs are initialised
+    pub drop_privileges: bool,
+}
+
+impl MatrixConfig {
+    /// Size of the whole canvas across every chained and parallel panel,
+    /// before any pixel mapper is applied
+    pub fn size(&self) -> Size {
+        Size::new(self.cols * self.chain_length, self.rows * self.parallel)
+    }
+}
+
+impl Default for MatrixConfig {
+    /// A single 64x32 panel on an Adafruit RGB matrix hat with the PWM mod
+    fn default() -> Self {
+        Self {
+            cols: 64,
+            rows: 32,
+            chain_length: 1,
+            parallel: 1,
+            hardware_mapping: String::from("adafruit-hat-pwm"),
+            led_rgb_sequence: String::from("rbg"),
+            pwm_bits: 11,
+            pwm_lsb_nanoseconds: 130,
+            pwm_dither_bits: 0,
+            scan_mode: 0,
+            row_addr_type: 0,
+            multiplexing: 0,
+            panel_type: String::new(),
+            pixel_mapper_config: String::new(),
+            hardware_pulsing: true,
+            inverse_colors: false,
+            limit_refresh: 0,
+            show_refresh_rate: false,
+            brightness: 100,
+            gpio_slowdown: 2,
+            daemon: false,
+            drop_privileges: false,
+        }
+    }
+}
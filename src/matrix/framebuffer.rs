@@ -0,0 +1,99 @@
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+/// In-memory RGB canvas that every screen draws into, independent of the
+/// display backend that eventually presents it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FrameBuffer {
+    size: Size,
+    pixels: Vec<Rgb888>,
+}
+
+impl FrameBuffer {
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            pixels: vec![Rgb888::BLACK; (size.width * size.height) as usize],
+        }
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        if point.x < 0
+            || point.y < 0
+            || point.x as u32 >= self.size.width
+            || point.y as u32 >= self.size.height
+        {
+            return None;
+        }
+
+        Some(point.y as usize * self.size.width as usize + point.x as usize)
+    }
+
+    /// Pixels in row-major order, starting from the top left corner
+    pub fn pixels(&self) -> &[Rgb888] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Rgb888] {
+        &mut self.pixels
+    }
+
+    /// Raw 8-bit RGB triplets, row-major with no header
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b()])
+            .collect()
+    }
+
+    pub fn save_rgb(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_rgb())
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), png::EncodingError> {
+        let file = File::create(path)?;
+
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgb())?;
+
+        Ok(())
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(i) = self.index(point) {
+                self.pixels[i] = color;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.pixels.fill(color);
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use embedded_graphics::prelude::*;
+use rpi_led_matrix::{LedCanvas, LedColor, LedMatrix, LedMatrixOptions, LedRuntimeOptions};
+
+use super::{DisplayBackend, FrameBuffer, MatrixConfig};
+
+/// Adafruit RGB matrix hat, driven through rpi-rgb-led-matrix
+pub struct HatBackend {
+    rpi_led_matrix: LedMatrix,
+    // always Some, except while being swapped onto the panel
+    rpi_led_canvas: Option<LedCanvas>,
+}
+
+impl HatBackend {
+    pub fn new(config: &MatrixConfig) -> Self {
+        let mut matrix_options = LedMatrixOptions::new();
+        matrix_options.set_brightness(config.brightness).unwrap();
+        matrix_options.set_cols(config.cols);
+        matrix_options.set_rows(config.rows);
+        matrix_options.set_chain_length(config.chain_length);
+        matrix_options.set_parallel(config.parallel);
+        matrix_options.set_hardware_mapping(&config.hardware_mapping);
+        matrix_options.set_led_rgb_sequence(&config.led_rgb_sequence);
+        matrix_options.set_pwm_bits(config.pwm_bits).unwrap();
+        matrix_options.set_pwm_lsb_nanoseconds(config.pwm_lsb_nanoseconds);
+        matrix_options.set_pwm_dither_bits(config.pwm_dither_bits);
+        matrix_options.set_scan_mode(config.scan_mode);
+        matrix_options.set_row_addr_type(config.row_addr_type);
+        matrix_options.set_multiplexing(config.multiplexing);
+        matrix_options.set_panel_type(&config.panel_type);
+        matrix_options.set_pixel_mapper_config(&config.pixel_mapper_config);
+        matrix_options.set_hardware_pulsing(config.hardware_pulsing);
+        matrix_options.set_inverse_colors(config.inverse_colors);
+        matrix_options.set_limit_refresh(config.limit_refresh);
+        matrix_options.set_refresh_rate(config.show_refresh_rate);
+
+        let mut runtime_options = LedRuntimeOptions::new();
+        runtime_options.set_gpio_slowdown(config.gpio_slowdown);
+        runtime_options.set_daemon(config.daemon);
+        runtime_options.set_drop_privileges(config.drop_privileges);
+
+        let matrix = LedMatrix::new(Some(matrix_options), Some(runtime_options)).unwrap();
+        let canvas = matrix.offscreen_canvas();
+
+        Self {
+            rpi_led_matrix: matrix,
+            rpi_led_canvas: Some(canvas),
+        }
+    }
+
+    /// Canvas size reported by the driver, which accounts for pixel mappers
+    pub fn size(&self) -> Size {
+        let (width, height) = self.rpi_led_canvas.as_ref().unwrap().canvas_size();
+        Size::new(width as u32, height as u32)
+    }
+}
+
+impl DisplayBackend for HatBackend {
+    fn present(&mut self, frame: &FrameBuffer) {
+        let mut canvas = self.rpi_led_canvas.take().unwrap();
+
+        let width = frame.size().width as usize;
+        for (i, color) in frame.pixels().iter().enumerate() {
+            canvas.set(
+                (i % width) as i32,
+                (i / width) as i32,
+                &LedColor {
+                    red: color.r(),
+                    green: color.g(),
+                    blue: color.b(),
+                },
+            );
+        }
+
+        self.rpi_led_canvas = Some(self.rpi_led_matrix.swap(canvas));
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.rpi_led_matrix.set_brightness(brightness);
+    }
+}
@@ -0,0 +1,10 @@
+use super::{DisplayBackend, FrameBuffer};
+
+/// Renders without any output device, for machines with no panel or display.
+/// Frames only live in the `Matrix` canvas, where they can be saved with
+/// `Matrix::save_snapshot`
+pub struct HeadlessBackend {}
+
+impl DisplayBackend for HeadlessBackend {
+    fn present(&mut self, _frame: &FrameBuffer) {}
+}
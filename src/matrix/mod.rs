@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+mod config;
+mod framebuffer;
+#[cfg(feature = "hat")]
+mod hat;
+mod headless;
+#[cfg(feature = "simulator")]
+mod simulator;
+#[cfg(feature = "terminal")]
+mod terminal;
+
+// rpi-rgb-led-matrix drives the Pi's GPIO directly, so Cargo.toml only pulls
+// it in for 32-bit Pi OS and this must match that target
+#[cfg(all(
+    feature = "hat",
+    not(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))
+))]
+compile_error!(
+    "the `hat` feature only builds for 32-bit Raspberry Pi OS, eg. \
+     `--target=armv7-unknown-linux-gnueabihf`, use `simulator` or `terminal` elsewhere"
+);
+
+pub use config::MatrixConfig;
+pub use framebuffer::FrameBuffer;
+
+use crate::inputs::keyboard::KeyboardSource;
+
+/// Somewhere a finished frame can be shown
+pub trait DisplayBackend {
+    /// Shows a completed frame
+    fn present(&mut self, frame: &FrameBuffer);
+
+    /// Sets panel brightness as a percentage, already clamped to 1..=100
+    fn set_brightness(&mut self, _brightness: u8) {}
+
+    /// Keys read by the backend's own window or terminal, can only be taken once
+    fn take_keyboard(&mut self) -> Option<KeyboardSource> {
+        None
+    }
+}
+
+/// Display backends that were compiled into this build
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BackendKind {
+    #[cfg(feature = "hat")]
+    Hat,
+    #[cfg(feature = "simulator")]
+    Simulator,
+    #[cfg(feature = "terminal")]
+    Terminal,
+    Headless,
+}
+
+impl Default for BackendKind {
+    /// Prefers real hardware, then a desktop window, when compiled in
+    fn default() -> Self {
+        #[cfg(feature = "hat")]
+        return BackendKind::Hat;
+        #[cfg(all(not(feature = "hat"), feature = "simulator"))]
+        return BackendKind::Simulator;
+        #[cfg(not(any(feature = "hat", feature = "simulator")))]
+        return BackendKind::Headless;
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "hat")]
+            "hat" => Ok(BackendKind::Hat),
+            #[cfg(feature = "simulator")]
+            "simulator" => Ok(BackendKind::Simulator),
+            #[cfg(feature = "terminal")]
+            "terminal" => Ok(BackendKind::Terminal),
+            "headless" => Ok(BackendKind::Headless),
+            _ => Err(format!("unknown or disabled display backend `{}`", s)),
+        }
+    }
+}
+
+pub struct Matrix {
+    backend: Box<dyn DisplayBackend>,
+    canvas: FrameBuffer,
+}
+
+impl Matrix {
+    /// Fails if the backend can't start, eg. the terminal backend without a terminal
+    pub fn new(config: &MatrixConfig, backend: BackendKind) -> Result<Self, String> {
+        let size = config.size();
+
+        // pixel mappers can change the hat's canvas size
+        let (backend, size): (Box<dyn DisplayBackend>, Size) = match backend {
+            #[cfg(feature = "hat")]
+            BackendKind::Hat => {
+                let hat = hat::HatBackend::new(config);
+                let size = hat.size();
+                (Box::new(hat), size)
+            }
+            #[cfg(feature = "simulator")]
+            BackendKind::Simulator => (Box::new(simulator::SimulatorBackend::new(size)), size),
+            #[cfg(feature = "terminal")]
+            BackendKind::Terminal => (Box::new(terminal::TerminalBackend::new()?), size),
+            BackendKind::Headless => (Box::new(headless::HeadlessBackend {}), size),
+        };
+
+        Ok(Self {
+            backend,
+            canvas: FrameBuffer::new(size),
+        })
+    }
+
+    pub fn get_canvas(&mut self) -> &mut FrameBuffer {
+        &mut self.canvas
+    }
+
+    pub fn pre_draw(&mut self) {
+        _ = self.canvas.clear(Rgb888::BLACK);
+    }
+
+    pub fn post_draw(&mut self) {
+        self.backend.present(&self.canvas);
+    }
+
+    pub fn take_keyboard(&mut self) -> Option<KeyboardSource> {
+        self.backend.take_keyboard()
+    }
+
+    /// Saves the most recently drawn frame, as raw RGB if the path ends in
+    /// `.rgb` and as PNG otherwise
+    pub fn save_snapshot(&self, path: &Path) -> Result<(), String> {
+        match path.extension() {
+            Some(x) if x == "rgb" => self.canvas.save_rgb(path).map_err(|e| e.to_string()),
+            _ => self.canvas.save_png(path).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) {
+        let clamped_brightness = num::clamp(brightness, 1, 100);
+        self.backend.set_brightness(clamped_brightness);
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::mpsc::Sender;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+use embedded_graphics_simulator::sdl2::Keycode;
+use embedded_graphics_simulator::{
+    OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+};
+
+use super::{DisplayBackend, FrameBuffer};
+use crate::inputs::keyboard::KeyboardSource;
+use crate::inputs::{Button, ButtonEvent, EventKind};
+
+/// Desktop window provided by embedded-graphics-simulator. Arrow keys,
+/// Enter/Space (select) and Backspace (back) are read back as buttons,
+/// closing the window or Esc quits
+pub struct SimulatorBackend {
+    sim_display: SimulatorDisplay<Rgb888>,
+    sim_window: Window,
+    /// keys are read from the window's events every frame
+    keys: Sender<ButtonEvent>,
+    keyboard: Option<KeyboardSource>,
+}
+
+impl SimulatorBackend {
+    pub fn new(size: Size) -> Self {
+        let output_settings = OutputSettingsBuilder::new().scale(10).build();
+
+        let sim_display: SimulatorDisplay<Rgb888> = SimulatorDisplay::new(size);
+
+        let sim_window = Window::new("smart-clock", &output_settings);
+        let (keys, keyboard) = KeyboardSource::channel();
+
+        Self {
+            sim_display,
+            sim_window,
+            keys,
+            keyboard: Some(keyboard),
+        }
+    }
+}
+
+fn get_button(keycode: Keycode) -> Option<Button> {
+    match keycode {
+        Keycode::Up => Some(Button::Up),
+        Keycode::Down => Some(Button::Down),
+        Keycode::Left | Keycode::Backspace => Some(Button::Left),
+        Keycode::Right => Some(Button::Right),
+        Keycode::Return | Keycode::KpEnter | Keycode::Space => Some(Button::Select),
+        _ => None,
+    }
+}
+
+impl DisplayBackend for SimulatorBackend {
+    fn present(&mut self, frame: &FrameBuffer) {
+        _ = self.sim_display.fill_contiguous(
+            &Rectangle::new(Point::zero(), frame.size()),
+            frame.pixels().iter().copied(),
+        );
+        self.sim_window.update(&self.sim_display);
+
+        // the window only has events once it has been updated
+        for event in self.sim_window.events() {
+            match event {
+                // held keys repeat at the OS key repeat rate
+                SimulatorEvent::KeyDown {
+                    keycode, repeat, ..
+                } => {
+                    if let Some(button) = get_button(keycode) {
+                        let kind = match repeat {
+                            true => EventKind::Repeat,
+                            false => EventKind::Pressed,
+                        };
+                        _ = self.keys.send(ButtonEvent { button, kind });
+                    }
+                }
+                SimulatorEvent::KeyUp { keycode, .. } => {
+                    if let Some(button) = get_button(keycode) {
+                        _ = self.keys.send(ButtonEvent {
+                            button,
+                            kind: EventKind::Released,
+                        });
+                    }
+                }
+                // the simulator turns Esc into a quit event as well
+                SimulatorEvent::Quit => std::process::exit(0),
+                _ => {}
+            }
+        }
+    }
+
+    fn take_keyboard(&mut self) -> Option<KeyboardSource> {
+        self.keyboard.take()
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::Write as _;
+use std::io::{stdin, stdout, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::{IntoRawMode, RawTerminal};
+
+use super::{DisplayBackend, FrameBuffer};
+use crate::inputs::keyboard::KeyboardSource;
+use crate::inputs::{Button, ButtonEvent};
+
+/// Draws the canvas into the controlling terminal with 24-bit ANSI colour,
+/// two pixel rows per character using the upper half block. Arrow keys and
+/// Enter are read back as buttons, `q` or Ctrl-C quits.
+pub struct TerminalBackend {
+    stdout: RawTerminal<Stdout>,
+    keyboard: Option<KeyboardSource>,
+    quit: Arc<AtomicBool>,
+    // skip redrawing identical frames, SSH sessions are slow
+    last_frame: Option<FrameBuffer>,
+}
+
+impl TerminalBackend {
+    /// Fails when stdout isn't a terminal, eg. under systemd or piped to a file
+    pub fn new() -> Result<Self, String> {
+        let mut stdout = stdout()
+            .into_raw_mode()
+            .map_err(|e| format!("the terminal backend needs a terminal: {}", e))?;
+        _ = write!(stdout, "{}{}", termion::clear::All, termion::cursor::Hide);
+        _ = stdout.flush();
+
+        let (sender, keyboard) = KeyboardSource::channel();
+        let quit = Arc::new(AtomicBool::new(false));
+
+        let quit_requested = quit.clone();
+        std::thread::spawn(move || {
+            for key in stdin().keys() {
+                let button = match key {
+                    Ok(Key::Up) => Button::Up,
+                    Ok(Key::Down) => Button::Down,
+                    Ok(Key::Left) => Button::Left,
+                    Ok(Key::Right) => Button::Right,
+                    Ok(Key::Char('\n')) => Button::Select,
+                    Ok(Key::Char('q')) | Ok(Key::Ctrl('c')) => {
+                        quit_requested.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    Ok(_) => continue,
+                    Err(_) => return,
+                };
+                // terminals only report key presses, held keys arrive as
+                // more presses at the OS key repeat rate
+                if sender.send(ButtonEvent::pressed(button)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            stdout,
+            keyboard: Some(keyboard),
+            quit,
+            last_frame: None,
+        })
+    }
+
+    fn render(frame: &FrameBuffer) -> String {
+        let size = frame.size();
+        let width = size.width as usize;
+        let pixels = frame.pixels();
+
+        let mut res = String::from(termion::cursor::Goto(1, 1));
+        for y in (0..size.height as usize).step_by(2) {
+            for x in 0..width {
+                let top = pixels[y * width + x];
+                let bottom = match pixels.get((y + 1) * width + x) {
+                    Some(color) => *color,
+                    None => Rgb888::BLACK,
+                };
+                _ = write!(
+                    res,
+                    "{}{}\u{2580}",
+                    termion::color::Fg(termion::color::Rgb(top.r(), top.g(), top.b())),
+                    termion::color::Bg(termion::color::Rgb(bottom.r(), bottom.g(), bottom.b())),
+                );
+            }
+            _ = write!(
+                res,
+                "{}{}\r\n",
+                termion::color::Fg(termion::color::Reset),
+                termion::color::Bg(termion::color::Reset)
+            );
+        }
+
+        res
+    }
+
+    fn restore(&mut self) {
+        _ = write!(
+            self.stdout,
+            "{}{}",
+            termion::style::Reset,
+            termion::cursor::Show
+        );
+        _ = self.stdout.flush();
+        _ = self.stdout.suspend_raw_mode();
+    }
+}
+
+impl DisplayBackend for TerminalBackend {
+    fn present(&mut self, frame: &FrameBuffer) {
+        if self.quit.load(Ordering::Relaxed) {
+            self.restore();
+            std::process::exit(0);
+        }
+
+        if self.last_frame.as_ref() == Some(frame) {
+            return;
+        }
+
+        _ = self
+            .stdout
+            .write_all(TerminalBackend::render(frame).as_bytes());
+        _ = self.stdout.flush();
+        self.last_frame = Some(frame.clone());
+    }
+
+    fn take_keyboard(&mut self) -> Option<KeyboardSource> {
+        self.keyboard.take()
+    }
+}
+
+impl Drop for TerminalBackend {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
@@ -0,0 +1,134 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Most update steps run in one frame. A longer stall, eg. the process
+/// being suspended, is dropped rather than fast-forwarded through
+static MAX_STEPS: u32 = 5;
+
+/// Paces the main loop at a target frame rate, with updates advancing in
+/// fixed steps of one frame time so behaviour doesn't depend on CPU speed
+pub struct Scheduler {
+    step: Duration,
+    /// when the last frame started
+    last_frame: Instant,
+    /// time that has passed but not been covered by an update step yet
+    backlog: Duration,
+}
+
+impl Scheduler {
+    pub fn new(fps: u32) -> Self {
+        let step = Duration::from_secs(1) / fps;
+
+        Self {
+            step,
+            // the first frame gets one update step straight away
+            last_frame: Instant::now() - step,
+            backlog: Duration::ZERO,
+        }
+    }
+
+    /// Fixed time every update step advances by
+    pub fn get_step(&self) -> Duration {
+        self.step
+    }
+
+    /// Sleeps until the next frame is due, then returns how many update
+    /// steps to run before drawing it
+    pub fn wait(&mut self) -> u32 {
+        let elapsed = self.last_frame.elapsed();
+        if elapsed < self.step {
+            sleep(self.step - elapsed);
+        }
+
+        let now = Instant::now();
+        let steps = self.advance(now - self.last_frame);
+        self.last_frame = now;
+
+        steps
+    }
+
+    /// Adds `elapsed` to the backlog and takes as many whole steps off it
+    /// as it covers
+    fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.backlog += elapsed;
+
+        let steps = (self.backlog.as_nanos() / self.step.as_nanos()) as u32;
+        match steps > MAX_STEPS {
+            true => {
+                self.backlog = Duration::ZERO;
+                MAX_STEPS
+            }
+            false => {
+                self.backlog -= self.step * steps;
+                steps
+            }
+        }
+    }
+}
+
+/// Fires once every `period` of update time
+pub struct Interval {
+    period: Duration,
+    elapsed: Duration,
+}
+
+impl Interval {
+    /// Fires on the first `tick`
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            elapsed: period,
+        }
+    }
+
+    /// Advances by `dt`, returns whether a period has passed since it last fired
+    pub fn tick(&mut self, dt: Duration) -> bool {
+        self.elapsed += dt;
+
+        if self.elapsed < self.period {
+            return false;
+        }
+
+        // keep the remainder so the average period stays exact, but don't
+        // fire again straight away after a stall
+        self.elapsed -= self.period;
+        if self.elapsed >= self.period {
+            self.elapsed = Duration::ZERO;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_carry_over_between_frames() {
+        let mut scheduler = Scheduler::new(50);
+        assert_eq!(scheduler.get_step(), Duration::from_millis(20));
+
+        assert_eq!(scheduler.advance(Duration::from_millis(30)), 1);
+        assert_eq!(scheduler.advance(Duration::from_millis(30)), 2);
+        assert_eq!(scheduler.advance(Duration::from_millis(10)), 0);
+    }
+
+    #[test]
+    fn long_stalls_are_dropped() {
+        let mut scheduler = Scheduler::new(50);
+
+        assert_eq!(scheduler.advance(Duration::from_secs(10)), MAX_STEPS);
+        assert_eq!(scheduler.advance(Duration::from_millis(20)), 1);
+    }
+
+    #[test]
+    fn intervals_fire_once_per_period() {
+        let mut interval = Interval::new(Duration::from_millis(50));
+        let step = Duration::from_millis(20);
+
+        // 20ms steps against a 50ms period fire every 40 or 60ms
+        let fired: Vec<bool> = (0..6).map(|_| interval.tick(step)).collect();
+        assert_eq!(fired, vec![true, false, true, false, true, false]);
+    }
+}
@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+use crate::config::TransitionsConfig;
+use crate::inputs::ButtonEvent;
+use crate::matrix::FrameBuffer;
+use crate::transition::{blend, Effect};
+
+/// One full-panel view of the clock, eg. the time or the region select menu.
+///
+/// Every frame the current screen gets `handle_input`, then `update`, then
+/// `draw`. Screens own their state, so they can be driven directly in tests
+pub trait Screen {
+    /// Unique name other screens use to switch to this one
+    fn name(&self) -> &'static str;
+
+    /// Whether there is anything to show, the carousel skips screens that
+    /// return false, eg. the weather before the first reading
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Called when this screen becomes the current screen
+    fn on_enter(&mut self) {}
+
+    /// Called when another screen replaces this one
+    fn on_exit(&mut self) {}
+
+    /// `events` are the button events since the last input poll. Returns the
+    /// name of the screen to switch to, if any
+    fn handle_input(&mut self, _events: &[ButtonEvent]) -> Option<&'static str> {
+        None
+    }
+
+    /// Advances the screen by `dt`, the time since the last frame
+    fn update(&mut self, _dt: Duration) {}
+
+    /// Draws onto a canvas that has already been cleared
+    fn draw(&mut self, canvas: &mut FrameBuffer);
+}
+
+/// A switch between screens that is still animating
+struct ActiveTransition {
+    /// screen being switched away from
+    from: usize,
+    effect: Effect,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+/// Every screen the clock can show, and which one is showing
+#[derive(Default)]
+pub struct ScreenRegistry {
+    screens: Vec<Box<dyn Screen>>,
+    current: Option<usize>,
+    /// `None` switches with a hard cut
+    transitions: Option<TransitionsConfig>,
+    transition: Option<ActiveTransition>,
+    /// offscreen canvases the outgoing and incoming screens draw into
+    /// during a transition
+    from_buffer: Option<FrameBuffer>,
+    to_buffer: Option<FrameBuffer>,
+}
+
+impl ScreenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a screen, replacing any screen registered under the same name
+    pub fn register(&mut self, screen: Box<dyn Screen>) {
+        match self.get_index(screen.name()) {
+            Some(i) => self.screens[i] = screen,
+            None => self.screens.push(screen),
+        }
+    }
+
+    fn get_index(&self, name: &str) -> Option<usize> {
+        self.screens.iter().position(|x| x.name() == name)
+    }
+
+    /// Animates every later switch, errors if a rule names a screen that
+    /// isn't registered
+    pub fn set_transitions(&mut self, config: &TransitionsConfig) -> Result<(), String> {
+        for rule in &config.rules {
+            for name in [&rule.from, &rule.to].into_iter().flatten() {
+                if !self.contains(name) {
+                    return Err(format!("transitions.rules: no screen named `{}`", name));
+                }
+            }
+        }
+
+        self.transitions = Some(config.clone());
+        Ok(())
+    }
+
+    /// Name of the screen being shown, `None` before the first `switch_to`
+    pub fn get_current(&self) -> Option<&'static str> {
+        self.current.map(|i| self.screens[i].name())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get_index(name).is_some()
+    }
+
+    /// Whether `name` is registered and has something to show
+    pub fn is_available(&self, name: &str) -> bool {
+        match self.get_index(name) {
+            Some(i) => self.screens[i].is_available(),
+            None => false,
+        }
+    }
+
+    /// Makes `name` the current screen, calling `on_exit` on the screen it
+    /// replaces and then `on_enter` on the new one
+    pub fn switch_to(&mut self, name: &str) -> Result<(), String> {
+        let next = match self.get_index(name) {
+            Some(x) => x,
+            None => return Err(format!("no screen named `{}`", name)),
+        };
+
+        if let Some(i) = self.current {
+            self.screens[i].on_exit();
+
+            // a switch part way through a transition cuts straight to the
+            // screen it was heading for
+            self.transition = match &self.transitions {
+                Some(x) if i != next => {
+                    let (effect, duration) =
+                        x.get_transition(self.screens[i].name(), self.screens[next].name());
+                    match effect == Effect::None || duration.is_zero() {
+                        true => None,
+                        false => Some(ActiveTransition {
+                            from: i,
+                            effect,
+                            duration,
+                            elapsed: Duration::ZERO,
+                        }),
+                    }
+                }
+                _ => None,
+            };
+        }
+        self.current = Some(next);
+        self.screens[next].on_enter();
+
+        Ok(())
+    }
+
+    pub fn handle_input(&mut self, events: &[ButtonEvent]) {
+        let next = match self.current {
+            Some(i) => self.screens[i].handle_input(events),
+            None => None,
+        };
+
+        if let Some(name) = next {
+            if let Err(e) = self.switch_to(name) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    /// Updates the current screen, and the outgoing one while a transition
+    /// is running so it doesn't freeze as it leaves
+    pub fn update(&mut self, dt: Duration) {
+        if let Some(i) = self.current {
+            self.screens[i].update(dt);
+        }
+
+        if let Some(transition) = &mut self.transition {
+            self.screens[transition.from].update(dt);
+
+            transition.elapsed += dt;
+            if transition.elapsed >= transition.duration {
+                self.transition = None;
+            }
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &mut FrameBuffer) {
+        let current = match self.current {
+            Some(x) => x,
+            None => return,
+        };
+
+        let transition = match &self.transition {
+            Some(x) => x,
+            None => {
+                self.screens[current].draw(canvas);
+                return;
+            }
+        };
+
+        let size = canvas.size();
+        let from_buffer = match &mut self.from_buffer {
+            Some(x) if x.size() == size => x,
+            x => x.insert(FrameBuffer::new(size)),
+        };
+        let to_buffer = match &mut self.to_buffer {
+            Some(x) if x.size() == size => x,
+            x => x.insert(FrameBuffer::new(size)),
+        };
+
+        _ = from_buffer.clear(Rgb888::BLACK);
+        self.screens[transition.from].draw(from_buffer);
+        _ = to_buffer.clear(Rgb888::BLACK);
+        self.screens[current].draw(to_buffer);
+
+        let progress = transition.elapsed.as_secs_f32() / transition.duration.as_secs_f32();
+        blend(transition.effect, from_buffer, to_buffer, progress, canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::inputs::events::presses;
+    use crate::inputs::Button;
+
+    /// Records every call it gets in `log`, switches to `next` on any button
+    struct TestScreen {
+        name: &'static str,
+        next: Option<&'static str>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Screen for TestScreen {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn on_enter(&mut self) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("enter {}", self.name));
+        }
+
+        fn on_exit(&mut self) {
+            self.log.lock().unwrap().push(format!("exit {}", self.name));
+        }
+
+        fn handle_input(&mut self, events: &[ButtonEvent]) -> Option<&'static str> {
+            match events.is_empty() {
+                true => None,
+                false => self.next,
+            }
+        }
+
+        fn update(&mut self, dt: Duration) {
+            let line = format!("update {} {}ms", self.name, dt.as_millis());
+            self.log.lock().unwrap().push(line);
+        }
+
+        fn draw(&mut self, _canvas: &mut FrameBuffer) {}
+    }
+
+    fn get_registry(log: &Arc<Mutex<Vec<String>>>) -> ScreenRegistry {
+        let mut registry = ScreenRegistry::new();
+        for (name, next) in [("first", Some("second")), ("second", Some("missing"))] {
+            registry.register(Box::new(TestScreen {
+                name,
+                next,
+                log: log.clone(),
+            }));
+        }
+        registry
+    }
+
+    #[test]
+    fn switching_calls_exit_then_enter() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = get_registry(&log);
+
+        assert_eq!(registry.get_current(), None);
+        registry.switch_to("first").unwrap();
+        registry.handle_input(&[]);
+        registry.update(Duration::from_millis(20));
+        registry.handle_input(&presses(&[Button::Select]));
+
+        assert_eq!(registry.get_current(), Some("second"));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "enter first",
+                "update first 20ms",
+                "exit first",
+                "enter second"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_screens_are_ignored() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = get_registry(&log);
+
+        assert!(registry.switch_to("missing").is_err());
+        registry.switch_to("second").unwrap();
+        registry.handle_input(&presses(&[Button::Select]));
+
+        assert_eq!(registry.get_current(), Some("second"));
+        assert_eq!(*log.lock().unwrap(), vec!["enter second"]);
+    }
+}
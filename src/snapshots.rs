@@ -0,0 +1,421 @@
+//! Golden-image tests for every screen.
+//!
+//! Each test renders a screen headlessly with a fixed clock, weather value and
+//! scripted button presses, then compares the final frame against a PNG in
+//! `tests/snapshots`. Run with `UPDATE_SNAPSHOTS=1` to (re)write the goldens
+//! after an intentional layout change. On a mismatch the rendered frame is
+//! saved to `target/snapshots` next to a pixel diff printed to the console.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+use crate::config::TransitionsConfig;
+use crate::inputs::scripted::ScriptedSource;
+use crate::inputs::{Button, ButtonEvent, EventKind, InputSource};
+use crate::matrix::{BackendKind, FrameBuffer, Matrix, MatrixConfig};
+use crate::screen::{Screen, ScreenRegistry};
+use crate::states::buttons::ButtonTestScreen;
+use crate::states::date::DateScreen;
+use crate::states::region_select::{get_cities, get_countries, RegionSelectScreen};
+use crate::states::time::TimeScreen;
+use crate::states::weather::WeatherScreen;
+use crate::states::SelectedTimezone;
+use crate::transition::Effect;
+use crate::weather::{Conditions, FixtureProvider, Reading, WeatherProvider};
+
+fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/snapshots")
+}
+
+/// Weather from `tests/fixtures/weather.json`
+fn fixture_conditions() -> Conditions {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/weather.json");
+    FixtureProvider::new(&path)
+        .fetch(&chrono_tz::Australia::Adelaide)
+        .unwrap()
+}
+
+/// Region select menu that saves any pick under `target` rather than to
+/// `storage.state_file`
+fn get_region_select() -> RegionSelectScreen {
+    RegionSelectScreen::new(SelectedTimezone::new(), &output_dir().join("state.toml"))
+}
+
+/// Time `render` advances each screen by per frame
+const FRAME_TIME: Duration = Duration::from_millis(20);
+
+/// Draws one frame per entry in `script`, passing that entry's events to
+/// `screen`, and returns the last frame
+fn render(screen: &mut dyn Screen, script: &[Vec<ButtonEvent>]) -> FrameBuffer {
+    render_with(&MatrixConfig::default(), screen, script)
+}
+
+/// `render` on a panel with different geometry
+fn render_with(
+    config: &MatrixConfig,
+    screen: &mut dyn Screen,
+    script: &[Vec<ButtonEvent>],
+) -> FrameBuffer {
+    let mut matrix = Matrix::new(config, BackendKind::Headless).unwrap();
+
+    screen.on_enter();
+    for events in script {
+        _ = screen.handle_input(events);
+        screen.update(FRAME_TIME);
+        matrix.pre_draw();
+        screen.draw(matrix.get_canvas());
+        matrix.post_draw();
+    }
+
+    matrix.get_canvas().clone()
+}
+
+/// Halfway through `effect` going from the region select menu to the time
+fn render_transition(effect: Effect) -> FrameBuffer {
+    let local_time = chrono_tz::Australia::Adelaide
+        .with_ymd_and_hms(2022, 10, 5, 13, 37, 42)
+        .unwrap();
+
+    let mut screens = ScreenRegistry::new();
+    screens.register(Box::new(get_region_select()));
+    screens.register(Box::new(TimeScreen::frozen(local_time, None)));
+    let transitions = TransitionsConfig {
+        effect,
+        duration_ms: 400,
+        rules: Vec::new(),
+    };
+    screens.set_transitions(&transitions).unwrap();
+
+    screens.switch_to(RegionSelectScreen::NAME).unwrap();
+    screens.switch_to(TimeScreen::NAME).unwrap();
+    screens.update(Duration::from_millis(200));
+
+    let mut matrix = Matrix::new(&MatrixConfig::default(), BackendKind::Headless).unwrap();
+    matrix.pre_draw();
+    screens.draw(matrix.get_canvas());
+    matrix.post_draw();
+
+    matrix.get_canvas().clone()
+}
+
+/// `count` frames that each press `button` once
+fn presses(button: Button, count: usize) -> Vec<Vec<ButtonEvent>> {
+    vec![vec![ButtonEvent::pressed(button)]; count]
+}
+
+fn load_png(path: &Path) -> Result<FrameBuffer, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(|e| e.to_string())?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    if info.color_type != png::ColorType::Rgb || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!(
+            "expected 8-bit RGB, found {:?} {:?}",
+            info.bit_depth, info.color_type
+        ));
+    }
+
+    let mut frame = FrameBuffer::new(Size::new(info.width, info.height));
+    _ = frame.fill_contiguous(
+        &frame.bounding_box(),
+        buf[..info.buffer_size()]
+            .chunks(3)
+            .map(|x| Rgb888::new(x[0], x[1], x[2])),
+    );
+
+    Ok(frame)
+}
+
+/// Human readable description of every pixel that differs between frames
+fn diff(expected: &FrameBuffer, actual: &FrameBuffer) -> Option<String> {
+    if expected.size() != actual.size() {
+        return Some(format!(
+            "size differs: expected {}x{}, rendered {}x{}",
+            expected.size().width,
+            expected.size().height,
+            actual.size().width,
+            actual.size().height
+        ));
+    }
+
+    let width = actual.size().width as usize;
+    let differences: Vec<(usize, Rgb888, Rgb888)> = expected
+        .pixels()
+        .iter()
+        .zip(actual.pixels())
+        .enumerate()
+        .filter(|(_, (e, a))| e != a)
+        .map(|(i, (e, a))| (i, *e, *a))
+        .collect();
+
+    if differences.is_empty() {
+        return None;
+    }
+
+    // '.' unlit, '#' lit, 'X' differs
+    let mut res = format!("{} pixels differ\n", differences.len());
+    for (i, pixel) in actual.pixels().iter().enumerate() {
+        if differences.iter().any(|(j, _, _)| *j == i) {
+            res.push('X');
+        } else if *pixel == Rgb888::BLACK {
+            res.push('.');
+        } else {
+            res.push('#');
+        }
+        if (i + 1) % width == 0 {
+            res.push('\n');
+        }
+    }
+    for (i, e, a) in differences.iter().take(20) {
+        res.push_str(&format!(
+            "({}, {}): expected #{:02x}{:02x}{:02x}, rendered #{:02x}{:02x}{:02x}\n",
+            i % width,
+            i / width,
+            e.r(),
+            e.g(),
+            e.b(),
+            a.r(),
+            a.g(),
+            a.b()
+        ));
+    }
+    if differences.len() > 20 {
+        res.push_str(&format!("... and {} more\n", differences.len() - 20));
+    }
+
+    Some(res)
+}
+
+fn assert_snapshot(name: &str, actual: &FrameBuffer) {
+    let golden = snapshot_dir().join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(snapshot_dir()).unwrap();
+        actual.save_png(&golden).unwrap();
+        return;
+    }
+
+    let expected = match load_png(&golden) {
+        Ok(x) => x,
+        Err(e) => panic!(
+            "could not load golden image {}: {}\nrun with UPDATE_SNAPSHOTS=1 to create it",
+            golden.display(),
+            e
+        ),
+    };
+
+    if let Some(difference) = diff(&expected, actual) {
+        std::fs::create_dir_all(output_dir()).unwrap();
+        let rendered = output_dir().join(format!("{}.png", name));
+        actual.save_png(&rendered).unwrap();
+
+        panic!(
+            "snapshot `{}` does not match {}\nrendered frame saved to {}\n{}",
+            name,
+            golden.display(),
+            rendered.display(),
+            difference
+        );
+    }
+}
+
+#[test]
+fn time() {
+    let local_time = chrono_tz::Australia::Adelaide
+        .with_ymd_and_hms(2022, 10, 5, 13, 37, 42)
+        .unwrap();
+    let reading = Reading {
+        conditions: fixture_conditions(),
+        timezone: chrono_tz::Australia::Adelaide,
+        fetched_at: local_time.with_timezone(&Utc) - chrono::Duration::minutes(5),
+    };
+
+    let mut screen = TimeScreen::frozen(local_time, Some(reading));
+    let frame = render(&mut screen, &[vec![]]);
+
+    assert_snapshot("time", &frame);
+}
+
+#[test]
+fn time_without_weather() {
+    let local_time = chrono_tz::Australia::Adelaide
+        .with_ymd_and_hms(2022, 10, 5, 13, 37, 42)
+        .unwrap();
+
+    let mut screen = TimeScreen::frozen(local_time, None);
+    let frame = render(&mut screen, &[vec![]]);
+
+    assert_snapshot("time_without_weather", &frame);
+}
+
+#[test]
+fn time_stale_weather() {
+    let local_time = chrono_tz::Australia::Adelaide
+        .with_ymd_and_hms(2022, 10, 5, 13, 37, 42)
+        .unwrap();
+    let reading = Reading {
+        conditions: fixture_conditions(),
+        timezone: chrono_tz::Australia::Adelaide,
+        fetched_at: local_time.with_timezone(&Utc) - chrono::Duration::hours(2),
+    };
+
+    let mut screen = TimeScreen::frozen(local_time, Some(reading));
+    let frame = render(&mut screen, &[vec![]]);
+
+    assert_snapshot("time_stale_weather", &frame);
+}
+
+#[test]
+fn date() {
+    let local_time = chrono_tz::Australia::Adelaide
+        .with_ymd_and_hms(2022, 10, 5, 13, 37, 42)
+        .unwrap();
+
+    let frame = render(&mut DateScreen::frozen(local_time), &[vec![]]);
+
+    assert_snapshot("date", &frame);
+}
+
+#[test]
+fn buttons() {
+    // A and Select held, the stick pushed up and to the right
+    let mut screen = ButtonTestScreen::frozen(1 << 6 | 1 << 14, (0.5, -1.0));
+    let script = vec![vec![ButtonEvent {
+        button: Button::Select,
+        kind: EventKind::LongPress,
+    }]];
+
+    let frame = render(&mut screen, &script);
+
+    assert_snapshot("buttons", &frame);
+}
+
+#[test]
+fn weather() {
+    let fetched_at = Utc.with_ymd_and_hms(2022, 10, 5, 3, 0, 0).unwrap();
+    let reading = Reading {
+        conditions: fixture_conditions(),
+        timezone: chrono_tz::Australia::Adelaide,
+        fetched_at,
+    };
+
+    let mut screen = WeatherScreen::frozen(Some(reading), fetched_at);
+    let frame = render(&mut screen, &[vec![]]);
+
+    assert_snapshot("weather", &frame);
+}
+
+#[test]
+fn region_select_countries_top() {
+    let frame = render(&mut get_region_select(), &[vec![]]);
+
+    assert_snapshot("region_select_countries_top", &frame);
+}
+
+#[test]
+fn region_select_countries_scrolled() {
+    // pressing up at the top of the list must not move the cursor, then
+    // three presses of down scroll it
+    let mut script = presses(Button::Up, 2);
+    script.extend(presses(Button::Down, 3));
+    let frame = render(&mut get_region_select(), &script);
+
+    assert_snapshot("region_select_countries_scrolled", &frame);
+}
+
+#[test]
+fn region_select_cities() {
+    let country = get_countries()
+        .iter()
+        .position(|x| *x == "Australia")
+        .unwrap();
+    let city = get_cities("Australia")
+        .iter()
+        .position(|x| *x == "Melbourne")
+        .unwrap();
+
+    let mut script = presses(Button::Down, country);
+    script.extend(presses(Button::Right, 1));
+    script.extend(presses(Button::Down, city));
+    let frame = render(&mut get_region_select(), &script);
+
+    assert_snapshot("region_select_cities", &frame);
+}
+
+#[test]
+fn region_select_back_to_countries() {
+    let mut script = presses(Button::Down, 4);
+    script.extend(presses(Button::Right, 1));
+    script.extend(presses(Button::Left, 1));
+    let frame = render(&mut get_region_select(), &script);
+
+    assert_snapshot("region_select_back_to_countries", &frame);
+}
+
+#[test]
+fn region_select_scripted() {
+    // tests/fixtures/inputs.toml presses the same buttons as
+    // region_select_back_to_countries, so it should end on the same frame
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/inputs.toml");
+    let mut source = ScriptedSource::load(&path).unwrap();
+    let script: Vec<Vec<ButtonEvent>> = (0..150).map(|_| source.poll(FRAME_TIME)).collect();
+
+    let frame = render(&mut get_region_select(), &script);
+
+    assert_snapshot("region_select_back_to_countries", &frame);
+}
+
+#[test]
+fn region_select_short_list() {
+    // Arctic only has Longyearbyen, so there is nothing to scroll
+    let country = get_countries().iter().position(|x| *x == "Arctic").unwrap();
+
+    let mut script = presses(Button::Down, country);
+    script.extend(presses(Button::Right, 1));
+    let frame = render(&mut get_region_select(), &script);
+
+    assert_snapshot("region_select_short_list", &frame);
+}
+
+#[test]
+fn region_select_tall_panel() {
+    let config = MatrixConfig {
+        rows: 64,
+        ..MatrixConfig::default()
+    };
+    let frame = render_with(&config, &mut get_region_select(), &presses(Button::Down, 5));
+
+    assert_snapshot("region_select_tall_panel", &frame);
+}
+
+#[test]
+fn transition_slide_left() {
+    assert_snapshot(
+        "transition_slide_left",
+        &render_transition(Effect::SlideLeft),
+    );
+}
+
+#[test]
+fn transition_crossfade() {
+    assert_snapshot(
+        "transition_crossfade",
+        &render_transition(Effect::Crossfade),
+    );
+}
+
+#[test]
+fn transition_dissolve() {
+    assert_snapshot("transition_dissolve", &render_transition(Effect::Dissolve));
+}
@@ -1,4 +0,0 @@
-pub enum State {
-    RegionSelect,
-    Time,
-}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle, Rectangle};
+use embedded_graphics::{
+    geometry::Point, geometry::Size, mono_font::ascii::*, pixelcolor::Rgb888, prelude::*,
+    text::Alignment, text::Text, Drawable,
+};
+
+use crate::config::{self, ButtonMapping};
+use crate::inputs::joy_featherwing::JoyFeatherwing;
+use crate::inputs::{Button, ButtonEvent, EventKind};
+use crate::matrix::FrameBuffer;
+use crate::screen::Screen;
+
+const COLOUR: Rgb888 = Rgb888::new(0xff, 0x0, 0x0);
+const DIM_COLOUR: Rgb888 = Rgb888::new(0x50, 0x0, 0x0);
+
+/// Single letter for a button's action
+fn get_initial(button: Button) -> &'static str {
+    match button {
+        Button::Up => "U",
+        Button::Down => "D",
+        Button::Left => "L",
+        Button::Right => "R",
+        Button::Select => "S",
+    }
+}
+
+fn get_event_name(event: &ButtonEvent) -> String {
+    let kind = match event.kind {
+        EventKind::Pressed => "press",
+        EventKind::Released => "release",
+        EventKind::LongPress => "long",
+        EventKind::Repeat => "repeat",
+    };
+    format!("{:?} {}", event.button, kind).to_lowercase()
+}
+
+/// Shows which joy featherwing buttons are held, the seesaw pin and action
+/// each is mapped to, the thumbstick position and the last button event, to
+/// check `input.buttons` against the hardware
+pub struct ButtonTestScreen {
+    buttons: Vec<ButtonMapping>,
+    /// bitmask of the seesaw pins held
+    held_pins: u32,
+    stick: (f32, f32),
+    last_event: Option<ButtonEvent>,
+    /// whether `update` follows the featherwing
+    live: bool,
+}
+
+impl ButtonTestScreen {
+    pub const NAME: &'static str = "buttons";
+
+    pub fn new() -> Self {
+        Self {
+            buttons: config::get().input.buttons.clone(),
+            held_pins: 0,
+            stick: (0.0, 0.0),
+            last_event: None,
+            live: true,
+        }
+    }
+
+    /// Always shows `held_pins` and `stick`, whatever the featherwing says
+    #[cfg(test)]
+    pub fn frozen(held_pins: u32, stick: (f32, f32)) -> Self {
+        Self {
+            held_pins,
+            stick,
+            live: false,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for ButtonTestScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen for ButtonTestScreen {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn handle_input(&mut self, events: &[ButtonEvent]) -> Option<&'static str> {
+        if let Some(x) = events.last() {
+            self.last_event = Some(*x);
+        }
+        None
+    }
+
+    fn update(&mut self, _dt: Duration) {
+        if self.live {
+            self.held_pins = JoyFeatherwing::get_held_pins();
+            self.stick = JoyFeatherwing::get_stick();
+        }
+    }
+
+    fn draw(&mut self, canvas: &mut FrameBuffer) {
+        let size = canvas.size();
+        let font: MonoTextStyle<Rgb888> = MonoTextStyle::new(&FONT_4X6, COLOUR);
+        let font_held: MonoTextStyle<Rgb888> = MonoTextStyle::new(&FONT_4X6, Rgb888::BLACK);
+
+        // a column per button: its name, lit while held, then pin and action
+        let column_width = size.width as i32 / self.buttons.len().max(1) as i32;
+        for (i, button) in self.buttons.iter().enumerate() {
+            let left = i as i32 * column_width;
+            let centre = left + column_width / 2;
+            let held = self.held_pins & 1 << button.pin != 0;
+
+            // too many buttons leave columns with no room for the outline
+            let inner_width = (column_width as u32).saturating_sub(2);
+            let cell = Rectangle::new(Point::new(left + 1, 0), Size::new(inner_width, 9));
+            let (style, text_style) = match held {
+                true => (PrimitiveStyle::with_fill(COLOUR), font_held),
+                false => (PrimitiveStyle::with_stroke(DIM_COLOUR, 1), font),
+            };
+            _ = cell.into_styled(style).draw(canvas);
+
+            // as much of the name as fits
+            let fits = inner_width as usize / 4;
+            let name: String = button.name.chars().take(fits).collect();
+            _ = Text::with_alignment(&name, Point::new(centre, 6), text_style, Alignment::Center)
+                .draw(canvas);
+            _ = Text::with_alignment(
+                &button.pin.to_string(),
+                Point::new(centre, 15),
+                font,
+                Alignment::Center,
+            )
+            .draw(canvas);
+            _ = Text::with_alignment(
+                get_initial(button.action),
+                Point::new(centre, 22),
+                font,
+                Alignment::Center,
+            )
+            .draw(canvas);
+        }
+
+        // last event along the bottom, next to the thumbstick
+        if let Some(event) = &self.last_event {
+            _ = Text::new(
+                &get_event_name(event),
+                Point::new(0, size.height as i32 - 2),
+                font,
+            )
+            .draw(canvas);
+        }
+
+        let stick_box = Rectangle::new(
+            Point::new(size.width as i32 - 8, size.height as i32 - 8),
+            Size::new(8, 8),
+        );
+        _ = stick_box
+            .into_styled(PrimitiveStyle::with_stroke(DIM_COLOUR, 1))
+            .draw(canvas);
+        let (x, y) = self.stick;
+        let dot =
+            stick_box.center() + Point::new((x * 2.0).round() as i32, (y * 2.0).round() as i32);
+        _ = Circle::with_center(dot, 2)
+            .into_styled(PrimitiveStyle::with_fill(COLOUR))
+            .draw(canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn more_buttons_than_columns() {
+        // 20 buttons on a 32 pixel wide panel leaves each a 1 pixel column
+        let buttons = (0..20)
+            .map(|pin| ButtonMapping::new("A", pin, Button::Select))
+            .collect();
+        let mut screen = ButtonTestScreen {
+            buttons,
+            ..ButtonTestScreen::frozen(1 << 3, (0.0, 0.0))
+        };
+
+        let mut canvas = FrameBuffer::new(Size::new(32, 32));
+        screen.draw(&mut canvas);
+
+        // no outlines or names, but the stick is still drawn
+        assert!(canvas.pixels()[..32 * 9]
+            .iter()
+            .all(|x| *x == Rgb888::BLACK));
+        assert_eq!(canvas.pixels()[32 * 31 - 1], DIM_COLOUR);
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::{
+    geometry::OriginDimensions, geometry::Point, mono_font::ascii::*, pixelcolor::Rgb888,
+    text::Alignment, text::Text, Drawable,
+};
+
+use crate::matrix::FrameBuffer;
+use crate::screen::Screen;
+
+use super::SelectedTimezone;
+
+/// The day of the week above the date
+pub struct DateScreen {
+    timezone: SelectedTimezone,
+    local_time: DateTime<Tz>,
+    /// whether `update` follows the system clock
+    live: bool,
+}
+
+impl DateScreen {
+    pub const NAME: &'static str = "date";
+
+    pub fn new(timezone: SelectedTimezone) -> Self {
+        Self {
+            local_time: timezone.get_local_time(),
+            timezone,
+            live: true,
+        }
+    }
+
+    /// Always shows `local_time`, whatever the real clock says
+    #[cfg(test)]
+    pub fn frozen(local_time: DateTime<Tz>) -> Self {
+        Self {
+            timezone: SelectedTimezone::new(),
+            local_time,
+            live: false,
+        }
+    }
+}
+
+impl Screen for DateScreen {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn update(&mut self, _dt: Duration) {
+        if self.live {
+            self.local_time = self.timezone.get_local_time();
+        }
+    }
+
+    fn draw(&mut self, canvas: &mut FrameBuffer) {
+        let size = canvas.size();
+        let centre = size.width as i32 / 2;
+        let height = size.height as i32;
+
+        let font_weekday: MonoTextStyle<Rgb888> =
+            MonoTextStyle::new(&FONT_6X10, Rgb888::new(0xff, 0x0, 0x0));
+        let font_date: MonoTextStyle<Rgb888> =
+            MonoTextStyle::new(&FONT_5X8, Rgb888::new(0xff, 0x0, 0x0));
+
+        // weekday just above the vertical centre, date below it
+        _ = Text::with_alignment(
+            self.local_time.format("%A").to_string().as_str(),
+            Point::new(centre, height / 2 - 2),
+            font_weekday,
+            Alignment::Center,
+        )
+        .draw(canvas);
+
+        _ = Text::with_alignment(
+            self.local_time.format("%-d %b %Y").to_string().as_str(),
+            Point::new(centre, height / 2 + 9),
+            font_date,
+            Alignment::Center,
+        )
+        .draw(canvas);
+    }
+}
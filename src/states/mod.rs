@@ -1,11 +1,60 @@
-use std::string::String;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
+
+pub mod buttons;
+pub mod date;
+pub mod persist;
 pub mod region_select;
 pub mod time;
+pub mod weather;
+
+/// The timezone picked in region select, handed to every screen that shows
+/// it and to the weather worker. Clones share the same timezone
+#[derive(Clone)]
+pub struct SelectedTimezone {
+    /// none until a region is picked or restored
+    timezone: Arc<Mutex<Option<Tz>>>,
+}
+
+impl SelectedTimezone {
+    pub fn new() -> Self {
+        Self {
+            timezone: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The picked timezone, UTC before one is picked
+    pub fn get(&self) -> Tz {
+        self.get_selected().unwrap_or(Tz::UTC)
+    }
+
+    pub fn get_selected(&self) -> Option<Tz> {
+        *self.timezone.lock().unwrap()
+    }
+
+    pub fn set(&self, timezone: Tz) {
+        *self.timezone.lock().unwrap() = Some(timezone);
+    }
+
+    /// Current time in the selected timezone
+    pub fn get_local_time(&self) -> DateTime<Tz> {
+        // get current UNIX Epoch time
+        let unix_epoch_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        self.get()
+            .timestamp_opt(unix_epoch_time.try_into().unwrap(), 0)
+            .unwrap()
+    }
+}
 
-lazy_static! {
-    /// country, city, timezone
-    static ref CURRENT_TIMEZONE: Mutex<chrono_tz::Tz> =
-        Mutex::new(chrono_tz::TZ_VARIANTS[0]);
+impl Default for SelectedTimezone {
+    fn default() -> Self {
+        Self::new()
+    }
 }
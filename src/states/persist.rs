@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Region chosen in the region select menu, saved so a restart can skip it
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SavedRegion {
+    /// IANA name, eg. `Australia/Adelaide`
+    pub timezone: String,
+    pub country_index: usize,
+    pub city_index: usize,
+}
+
+impl SavedRegion {
+    /// Reads a previously saved region, `None` if nothing usable was saved
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+
+        match toml::from_str(&contents) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                eprintln!("ignoring saved region in {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Writes to a temporary file first, so losing power part way through
+    /// can't leave a truncated file behind
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+        std::fs::rename(&temp_path, path).map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
@@ -1,63 +1,73 @@
 use std::collections::HashSet;
 use std::num::IntErrorKind;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
 
 use embedded_graphics::{
-    geometry::Point, mono_font::ascii::*, mono_font::*, pixelcolor::Rgb888, text::Alignment,
-    text::Text, Drawable,
+    geometry::OriginDimensions, geometry::Point, mono_font::ascii::*, mono_font::*,
+    pixelcolor::Rgb888, text::Alignment, text::Text, Drawable,
 };
 
 use chrono_tz::TZ_VARIANTS;
 
-use crate::states::CURRENT_TIMEZONE;
-use crate::Button;
-use crate::JoyFeatherwing;
-use crate::Matrix;
-use crate::State;
+use crate::inputs::{Button, ButtonEvent, EventKind};
+use crate::matrix::FrameBuffer;
+use crate::screen::Screen;
+use crate::states::persist::SavedRegion;
+use crate::states::time::TimeScreen;
+use crate::states::SelectedTimezone;
 
 enum RowType {
-    REGULAR,
-    HEADING,
-    SELECTED,
+    Regular,
+    Heading,
+    Selected,
+}
+
+const fn get_row_point(row_num: i32, row_type: &RowType) -> Result<Point, IntErrorKind> {
+    use RowType::*;
+
+    if row_num < 0 {
+        return Err(IntErrorKind::InvalidDigit);
+    }
+
+    let padding = 1;
+    let font_height = 6;
+    Ok(Point::new(
+        match row_type {
+            Heading => 1,
+            Regular => 5,
+            Selected => 3,
+        },
+        font_height - 1
+            + (row_num * (padding + font_height)
+                + match row_num {
+                    0 => 0,
+                    _ => 2,
+                }),
+    ))
+}
+
+/// Number of option rows that fit below the heading
+fn get_visible_rows(height: u32) -> usize {
+    let mut rows = 0;
+    // leave a pixel of padding below the last row
+    while get_row_point(rows + 1, &RowType::Regular).unwrap().y + 1 < height as i32 {
+        rows += 1;
+    }
+    rows as usize
 }
 
 // LAYOUT ---
-// total height available: 32px
 // heading: 1px padding top/bottom + 8px = 10px
-// unselected text rows = 6px = 12px
-// selected text row: 6px + 1px padding top/bottom = 8px
+// every option row below it: 6px + 1px padding = 7px, as many as fit
+// eg. 32px high: heading + 3 rows, 64px high: heading + 7 rows
 fn draw_menu_option(
-    matrix: &mut Matrix,
+    canvas: &mut FrameBuffer,
     text: &str,
     row_num: usize,
     row_type: &RowType,
 ) -> Result<(), String> {
     use RowType::*;
 
-    const fn get_row_point(row_num: i32, row_type: &RowType) -> Result<Point, IntErrorKind> {
-        use RowType::*;
-
-        if row_num < 0 {
-            return Err(IntErrorKind::InvalidDigit);
-        }
-
-        let padding = 1;
-        let font_height = 6;
-        return Ok(Point::new(
-            match row_type {
-                HEADING => 1,
-                REGULAR => 5,
-                SELECTED => 3,
-            },
-            font_height - 1
-                + (row_num * (padding + font_height)
-                    + match row_num {
-                        0 => 0,
-                        _ => 2,
-                    }),
-        ));
-    }
-
     let font_regular: MonoTextStyle<Rgb888> =
         MonoTextStyle::new(&FONT_4X6, Rgb888::new(0xff, 0xff, 0xff));
     let font_selected: MonoTextStyle<Rgb888> =
@@ -67,28 +77,44 @@ fn draw_menu_option(
         text,
         get_row_point(row_num as i32, row_type).unwrap(),
         match row_type {
-            SELECTED => font_selected,
+            Selected => font_selected,
             _ => font_regular,
         },
         Alignment::Left,
     )
-    .draw(matrix.get_canvas());
+    .draw(canvas);
 
     Ok(())
 }
 
+/// Draws as many `options` as fit on the canvas, scrolled to keep the
+/// `selected` option in the middle where possible
+fn draw_menu_options(canvas: &mut FrameBuffer, options: &[&str], selected: usize) {
+    use RowType::*;
+
+    let rows = get_visible_rows(canvas.size().height);
+    let first = selected
+        .saturating_sub(rows / 2)
+        .min(options.len().saturating_sub(rows));
+
+    for (i, option) in options.iter().enumerate().skip(first).take(rows) {
+        let row_type = match i == selected {
+            true => &Selected,
+            false => &Regular,
+        };
+        _ = draw_menu_option(canvas, option, i - first + 1, row_type);
+    }
+}
+
 /// country, city, TZ
-fn get_countries() -> Vec<&'static str> {
+pub fn get_countries() -> Vec<&'static str> {
     let mut countries: HashSet<&str> = HashSet::new();
 
     for tz in TZ_VARIANTS {
         let collection: Vec<&str> = tz.name().split("/").collect();
-        match collection.get(0) {
-            Some(x) => {
-                countries.insert(x);
-            }
-            None => {}
-        };
+        if let Some(x) = collection.first() {
+            countries.insert(x);
+        }
     }
 
     let mut res: Vec<&'static str> = countries.into_iter().collect();
@@ -96,17 +122,15 @@ fn get_countries() -> Vec<&'static str> {
     res
 }
 
-fn get_cities(country: &str) -> Vec<&'static str> {
+pub fn get_cities(country: &str) -> Vec<&'static str> {
     let mut cities: HashSet<&str> = HashSet::new();
 
     for tz in TZ_VARIANTS {
         let collection: Vec<&str> = tz.name().split("/").collect();
         let a = String::from(country);
-        let b = String::from(*collection.get(0).unwrap());
-        if a == b {
-            if collection.get(1).is_some() {
-                cities.insert(collection.get(1).unwrap());
-            }
+        let b = String::from(*collection.first().unwrap());
+        if a == b && collection.get(1).is_some() {
+            cities.insert(collection.get(1).unwrap());
         };
     }
 
@@ -119,7 +143,7 @@ fn get_timezone(country: &str, city: &str) -> Option<chrono_tz::Tz> {
     for tz in TZ_VARIANTS {
         let collection: Vec<&str> = tz.name().split("/").collect();
         let a = String::from(country);
-        let b = String::from(*collection.get(0).unwrap());
+        let b = String::from(*collection.first().unwrap());
         if a == b {
             if collection.get(1).is_some() {
                 let x = String::from(*collection.get(1).unwrap());
@@ -137,173 +161,315 @@ fn get_timezone(country: &str, city: &str) -> Option<chrono_tz::Tz> {
     None
 }
 
-lazy_static! {
-    static ref FRAME_COUNT: Mutex<usize> = Mutex::new(0);
-    static ref FRAMES_SINCE_LAST_INPUT_POLL: Mutex<usize> = Mutex::new(0);
-    static ref COUNTRY_INDEX: Mutex<usize> = Mutex::new(0);
-    static ref COUNTRY_SELECTED: Mutex<bool> = Mutex::new(false);
-    static ref CITY_INDEX: Mutex<usize> = Mutex::new(0);
+/// Makes `timezone` current and saves it to `path`, along with the menu
+/// position it was chosen from, so it survives a restart
+fn select_timezone(
+    selected: &SelectedTimezone,
+    path: &Path,
+    timezone: chrono_tz::Tz,
+    country_index: usize,
+    city_index: usize,
+) {
+    selected.set(timezone);
+
+    let saved = SavedRegion {
+        timezone: String::from(timezone.name()),
+        country_index,
+        city_index,
+    };
+    if let Err(e) = saved.save(path) {
+        eprintln!("failed to save region to {}: {}", path.display(), e);
+    }
 }
 
-pub fn region_select_state(matrix: &mut Matrix) -> State {
-    use RowType::*;
-    use State::*;
+/// Two level menu, countries then their cities, that picks the timezone
+pub struct RegionSelectScreen {
+    timezone: SelectedTimezone,
+    /// where a picked region is saved, eg. `storage.state_file`
+    state_file: PathBuf,
+    country_index: usize,
+    country_selected: bool,
+    city_index: usize,
+}
 
-    // acquire locks on state variables
-    let current_framecount = FRAME_COUNT.lock().unwrap();
-    let mut current_frames_since_last_input_poll = FRAMES_SINCE_LAST_INPUT_POLL.lock().unwrap();
+impl RegionSelectScreen {
+    pub const NAME: &'static str = "region_select";
 
-    let mut country_index = COUNTRY_INDEX.lock().unwrap();
-    let mut country_selected = COUNTRY_SELECTED.lock().unwrap();
-    let mut city_index = CITY_INDEX.lock().unwrap();
+    /// Starts at the top of the country list, picking into `timezone` and
+    /// saving the pick to `state_file`
+    pub fn new(timezone: SelectedTimezone, state_file: &Path) -> Self {
+        Self {
+            timezone,
+            state_file: state_file.to_path_buf(),
+            country_index: 0,
+            country_selected: false,
+            city_index: 0,
+        }
+    }
+
+    /// Makes a saved region current and moves the menu to it. Returns false
+    /// if the saved timezone no longer exists
+    pub fn restore(&mut self, saved: &SavedRegion) -> bool {
+        let timezone: chrono_tz::Tz = match saved.timezone.parse() {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
 
-    let input_poll_interval: usize = 5;
+        // indices are only a hint, the timezone database may have changed since
+        // zones like America/Argentina/Buenos_Aires are listed under their
+        // second part, same as `get_cities`
+        let mut parts = saved.timezone.split('/');
+        let country = parts.next().unwrap_or("");
+        let city = parts.next().unwrap_or("");
 
-    let current_country = get_countries()[*country_index];
-    let current_city = match *country_selected {
-        true => get_cities(current_country)[*city_index],
-        false => "",
-    };
+        let countries = get_countries();
+        self.country_index = match countries.get(saved.country_index) {
+            Some(x) if *x == country => saved.country_index,
+            _ => countries.iter().position(|x| *x == country).unwrap_or(0),
+        };
+        let cities = get_cities(country);
+        self.city_index = match cities.get(saved.city_index) {
+            Some(x) if *x == city => saved.city_index,
+            _ => cities.iter().position(|x| *x == city).unwrap_or(0),
+        };
+        self.country_selected = false;
+
+        self.timezone.set(timezone);
+
+        true
+    }
+}
+
+impl Screen for RegionSelectScreen {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn handle_input(&mut self, events: &[ButtonEvent]) -> Option<&'static str> {
+        let current_country = get_countries()[self.country_index];
+        let current_city = match self.country_selected {
+            true => get_cities(current_country)[self.city_index],
+            false => "",
+        };
+
+        for event in events {
+            // holding up or down scrolls, everything else acts once per press
+            let button = match (event.kind, event.button) {
+                (EventKind::Pressed, x) => x,
+                (EventKind::Repeat, x @ (Button::Up | Button::Down)) => x,
+                _ => continue,
+            };
 
-    if *current_frames_since_last_input_poll == input_poll_interval {
-        let buttons = JoyFeatherwing::get_joy_buttons();
-        for button in buttons {
             match button {
                 Button::Down => {
-                    if *country_selected {
+                    if self.country_selected {
                         // test for city down
-                        if *city_index < get_cities(current_country).len() - 1 {
-                            *city_index += 1;
+                        if self.city_index < get_cities(current_country).len() - 1 {
+                            self.city_index += 1;
                         }
                     } else {
                         // test for country down
-                        if *country_index < get_countries().len() - 1 {
-                            *country_index += 1;
+                        if self.country_index < get_countries().len() - 1 {
+                            self.country_index += 1;
                         }
                     }
                 }
                 Button::Left => {
-                    // back out of menu if possible
-                    if *country_selected {
-                        *country_selected = false;
-                    }
+                    // back out of the city list, if in it
+                    self.country_selected = false;
                 }
                 Button::Right => {
                     // select country or city
-                    if *country_selected {
-                        *CURRENT_TIMEZONE.lock().unwrap() =
-                            get_timezone(current_country, current_city).unwrap();
-                        return State::Time;
+                    if self.country_selected {
+                        select_timezone(
+                            &self.timezone,
+                            &self.state_file,
+                            get_timezone(current_country, current_city).unwrap(),
+                            self.country_index,
+                            self.city_index,
+                        );
+                        return Some(TimeScreen::NAME);
                     } else {
-                        *country_selected = true;
-                        match get_timezone(current_country, "") {
-                            Some(x) => {
-                                *CURRENT_TIMEZONE.lock().unwrap() = x;
-                                return State::Time;
-                            }
-                            None => {}
+                        self.country_selected = true;
+                        if let Some(x) = get_timezone(current_country, "") {
+                            select_timezone(
+                                &self.timezone,
+                                &self.state_file,
+                                x,
+                                self.country_index,
+                                0,
+                            );
+                            return Some(TimeScreen::NAME);
                         };
+                        // the city index may be left over from a larger country
+                        let cities = get_cities(current_country).len();
+                        self.city_index = self.city_index.min(cities.saturating_sub(1));
                     }
                 }
                 Button::Up => {
-                    if *country_selected {
+                    if self.country_selected {
                         // test for city up
-                        if *city_index != 0 {
-                            *city_index -= 1;
+                        if self.city_index != 0 {
+                            self.city_index -= 1;
                         }
                     } else {
                         // test for country up
-                        if *country_index != 0 {
-                            *country_index -= 1;
+                        if self.country_index != 0 {
+                            self.country_index -= 1;
                         }
                     }
                 }
                 _ => {}
             }
         }
-        *current_frames_since_last_input_poll = 0;
+
+        None
     }
-    *current_frames_since_last_input_poll += 1;
-
-    // heading
-    _ = draw_menu_option(matrix, "Region:", 0, &HEADING);
-
-    if !*country_selected {
-        // provide country options
-        if *country_index == 0 {
-            _ = draw_menu_option(matrix, get_countries()[*country_index], 1, &SELECTED);
-            _ = draw_menu_option(matrix, get_countries()[*country_index + 1], 2, &REGULAR);
-            _ = draw_menu_option(matrix, get_countries()[*country_index + 2], 3, &REGULAR);
-        } else if *country_index == get_countries().len() - 1 {
-            _ = draw_menu_option(matrix, get_countries()[*country_index - 3], 1, &REGULAR);
-            _ = draw_menu_option(matrix, get_countries()[*country_index - 2], 2, &REGULAR);
-            _ = draw_menu_option(matrix, get_countries()[*country_index - 1], 3, &SELECTED);
-        } else {
-            _ = draw_menu_option(matrix, get_countries()[*country_index - 1], 1, &REGULAR);
-            _ = draw_menu_option(matrix, get_countries()[*country_index], 2, &SELECTED);
-            _ = draw_menu_option(matrix, get_countries()[*country_index + 1], 3, &REGULAR);
-        }
-    } else {
-        // provide city options
-        if *city_index == 0 {
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index],
-                1,
-                &SELECTED,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index + 1],
-                2,
-                &REGULAR,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index + 2],
-                3,
-                &REGULAR,
-            );
-        } else if *city_index == get_cities(current_country).len() - 1 {
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index - 3],
-                1,
-                &REGULAR,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index - 2],
-                2,
-                &REGULAR,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index - 1],
-                3,
-                &SELECTED,
-            );
+
+    fn draw(&mut self, canvas: &mut FrameBuffer) {
+        use RowType::*;
+
+        // heading
+        _ = draw_menu_option(canvas, "Region:", 0, &Heading);
+
+        let current_country = get_countries()[self.country_index];
+        if !self.country_selected {
+            // provide country options
+            draw_menu_options(canvas, &get_countries(), self.country_index);
         } else {
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index - 1],
-                1,
-                &REGULAR,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index],
-                2,
-                &SELECTED,
-            );
-            _ = draw_menu_option(
-                matrix,
-                get_cities(current_country)[*city_index + 1],
-                3,
-                &REGULAR,
-            );
+            // provide city options
+            draw_menu_options(canvas, &get_cities(current_country), self.city_index);
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inputs::events::presses;
+
+    /// Saves to a file of its own so tests don't touch `storage.state_file`
+    fn get_state_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("smart-clock-{}-{}.toml", name, std::process::id()))
+    }
+
+    fn get_screen(timezone: SelectedTimezone) -> RegionSelectScreen {
+        RegionSelectScreen::new(timezone, &get_state_file("unsaved"))
+    }
+
+    fn get_country_index(country: &str) -> usize {
+        get_countries().iter().position(|x| *x == country).unwrap()
+    }
+
+    #[test]
+    fn cursor_stays_inside_the_list() {
+        let mut screen = get_screen(SelectedTimezone::new());
+
+        assert_eq!(
+            screen.handle_input(&presses(&[Button::Up, Button::Up])),
+            None
+        );
+        assert_eq!(screen.country_index, 0);
 
-    return RegionSelect;
+        let last = get_countries().len() - 1;
+        let buttons = vec![Button::Down; last + 3];
+        assert_eq!(screen.handle_input(&presses(&buttons)), None);
+        assert_eq!(screen.country_index, last);
+    }
+
+    #[test]
+    fn left_backs_out_of_the_city_list() {
+        let mut screen = get_screen(SelectedTimezone::new());
+        screen.country_index = get_country_index("Australia");
+
+        assert_eq!(
+            screen.handle_input(&presses(&[Button::Right, Button::Down])),
+            None
+        );
+        assert!(screen.country_selected);
+        assert_eq!(screen.city_index, 1);
+
+        assert_eq!(screen.handle_input(&presses(&[Button::Left])), None);
+        assert!(!screen.country_selected);
+    }
+
+    #[test]
+    fn holding_scrolls_but_does_not_select() {
+        let mut screen = get_screen(SelectedTimezone::new());
+        let repeat = |button| ButtonEvent {
+            button,
+            kind: EventKind::Repeat,
+        };
+
+        let events = vec![repeat(Button::Down); 3];
+        assert_eq!(screen.handle_input(&events), None);
+        assert_eq!(screen.country_index, 3);
+
+        assert_eq!(screen.handle_input(&[repeat(Button::Right)]), None);
+        assert!(!screen.country_selected);
+    }
+
+    #[test]
+    fn restore_corrects_stale_indices() {
+        let timezone = SelectedTimezone::new();
+        let mut screen = get_screen(timezone.clone());
+        let saved = SavedRegion {
+            timezone: String::from("Australia/Melbourne"),
+            country_index: 0,
+            city_index: 0,
+        };
+
+        assert!(screen.restore(&saved));
+        assert_eq!(screen.country_index, get_country_index("Australia"));
+        assert_eq!(get_cities("Australia")[screen.city_index], "Melbourne");
+        assert_eq!(timezone.get(), chrono_tz::Australia::Melbourne);
+
+        let saved = SavedRegion {
+            timezone: String::from("America/Argentina/Buenos_Aires"),
+            ..saved
+        };
+        assert!(screen.restore(&saved));
+        assert_eq!(get_cities("America")[screen.city_index], "Argentina");
+
+        let saved = SavedRegion {
+            timezone: String::from("Nowhere/Atlantis"),
+            ..saved
+        };
+        assert!(!screen.restore(&saved));
+    }
+
+    #[test]
+    fn picking_a_city_saves_it() {
+        let path = get_state_file("picked");
+        let timezone = SelectedTimezone::new();
+        let mut screen = RegionSelectScreen::new(timezone.clone(), &path);
+        let country_index = get_country_index("Australia");
+        let city_index = get_cities("Australia")
+            .iter()
+            .position(|x| *x == "Melbourne")
+            .unwrap();
+
+        screen.country_index = country_index;
+        assert_eq!(screen.handle_input(&presses(&[Button::Right])), None);
+        screen.city_index = city_index;
+        assert_eq!(
+            screen.handle_input(&presses(&[Button::Right])),
+            Some(TimeScreen::NAME)
+        );
+        assert_eq!(
+            timezone.get_selected(),
+            Some(chrono_tz::Australia::Melbourne)
+        );
+
+        let saved = SavedRegion::load(&path);
+        _ = std::fs::remove_file(&path);
+        assert_eq!(
+            saved,
+            Some(SavedRegion {
+                timezone: String::from("Australia/Melbourne"),
+                country_index,
+                city_index,
+            })
+        );
+    }
 }
@@ -1,77 +1,104 @@
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
-use chrono::TimeZone;
+use chrono::DateTime;
+use chrono_tz::Tz;
 use embedded_graphics::mono_font::MonoTextStyle;
 use embedded_graphics::{
-    geometry::Point, mono_font::ascii::*, mono_font::*, pixelcolor::Rgb888, text::Alignment,
-    text::Text, Drawable,
+    geometry::OriginDimensions, geometry::Point, mono_font::ascii::*, pixelcolor::Rgb888,
+    text::Alignment, text::Text, Drawable,
 };
-use openweathermap::blocking::weather;
 
-use crate::Matrix;
-use crate::State;
-use crate::State::*;
+use crate::matrix::FrameBuffer;
+use crate::screen::Screen;
+use crate::weather::{get_reading, Reading};
 
-use super::CURRENT_TIMEZONE;
+use super::SelectedTimezone;
 
-lazy_static! {
-    static ref CURRENT_TEMPERATURE: Mutex<f64> = Mutex::new({
-        let tz = &*CURRENT_TIMEZONE.lock().unwrap().name();
-        let collecion: Vec<&str> = tz.split('/').collect();
-        let current_city = collecion[1];
-
-        let current_weather = weather(
-            current_city,
-            "metric",
-            "en",
-            "8f05f2ea5cefe45e3d51e3df919359a6",
-        );
-
-        current_weather.unwrap().main.temp
-    });
-}
-
-fn draw_time(matrix: &mut Matrix) {
+fn draw_time(canvas: &mut FrameBuffer, local_time: &DateTime<Tz>) {
     let font_red: MonoTextStyle<Rgb888> =
         MonoTextStyle::new(&FONT_7X13, Rgb888::new(0xff, 0x0, 0x0));
 
-    // get current UNIX Epoch time
-
-    let unix_epoch_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let local_time = CURRENT_TIMEZONE
-        .lock()
-        .unwrap()
-        .timestamp(unix_epoch_time.try_into().unwrap(), 0);
+    // baseline just above the vertical centre
+    let height = canvas.size().height as i32;
 
     _ = Text::with_alignment(
         local_time.format("%H:%M:%S").to_string().as_str(),
-        Point::new(1, 15),
+        Point::new(1, height / 2 - 1),
         font_red,
         Alignment::Left,
     )
-    .draw(matrix.get_canvas());
+    .draw(canvas);
 }
 
-fn draw_temperature(matrix: &mut Matrix) {
-    let font_red: MonoTextStyle<Rgb888> =
-        MonoTextStyle::new(&FONT_4X6, Rgb888::new(0xff, 0x0, 0x0));
+fn draw_temperature(canvas: &mut FrameBuffer, reading: &Reading, local_time: &DateTime<Tz>) {
+    // dimmed once the reading is too old to trust
+    let colour = match reading.is_stale(local_time) {
+        true => Rgb888::new(0x40, 0x0, 0x0),
+        false => Rgb888::new(0xff, 0x0, 0x0),
+    };
+    let font_red: MonoTextStyle<Rgb888> = MonoTextStyle::new(&FONT_4X6, colour);
+
+    // one row of text above the bottom edge
+    let height = canvas.size().height as i32;
 
     _ = Text::with_alignment(
-        CURRENT_TEMPERATURE.lock().unwrap().to_string().as_str(),
-        Point::new(1, 26),
+        reading.conditions.temperature.to_string().as_str(),
+        Point::new(1, height - 6),
         font_red,
         Alignment::Left,
     )
-    .draw(matrix.get_canvas());
+    .draw(canvas);
 }
 
-pub fn time_state(matrix: &mut Matrix) -> State {
-    draw_time(matrix);
-    draw_temperature(matrix);
+/// The time, with the temperature below it when there is a reading
+pub struct TimeScreen {
+    timezone: SelectedTimezone,
+    local_time: DateTime<Tz>,
+    reading: Option<Reading>,
+    /// whether `update` follows the system clock and weather worker
+    live: bool,
+}
+
+impl TimeScreen {
+    pub const NAME: &'static str = "time";
+
+    pub fn new(timezone: SelectedTimezone) -> Self {
+        Self {
+            local_time: timezone.get_local_time(),
+            reading: get_reading(timezone.get()),
+            timezone,
+            live: true,
+        }
+    }
+
+    /// Always shows `local_time` and `reading`, whatever the real clock says
+    #[cfg(test)]
+    pub fn frozen(local_time: DateTime<Tz>, reading: Option<Reading>) -> Self {
+        Self {
+            timezone: SelectedTimezone::new(),
+            local_time,
+            reading,
+            live: false,
+        }
+    }
+}
+
+impl Screen for TimeScreen {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn update(&mut self, _dt: Duration) {
+        if self.live {
+            self.local_time = self.timezone.get_local_time();
+            self.reading = get_reading(self.timezone.get());
+        }
+    }
 
-    return Time;
+    fn draw(&mut self, canvas: &mut FrameBuffer) {
+        draw_time(canvas, &self.local_time);
+        if let Some(x) = &self.reading {
+            draw_temperature(canvas, x, &self.local_time);
+        }
+    }
 }
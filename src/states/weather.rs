@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::{
+    geometry::OriginDimensions, geometry::Point, mono_font::ascii::*, pixelcolor::Rgb888,
+    text::Alignment, text::Text, Drawable,
+};
+
+use crate::config;
+use crate::matrix::FrameBuffer;
+use crate::screen::Screen;
+use crate::weather::{get_reading, Condition, Reading};
+
+use super::SelectedTimezone;
+
+fn get_description(condition: Condition) -> &'static str {
+    match condition {
+        Condition::Clear => "Clear",
+        Condition::PartlyCloudy => "Partly cloudy",
+        Condition::Cloudy => "Cloudy",
+        Condition::Fog => "Fog",
+        Condition::Drizzle => "Drizzle",
+        Condition::Rain => "Rain",
+        Condition::Snow => "Snow",
+        Condition::Thunderstorm => "Thunderstorm",
+        Condition::Unknown => "",
+    }
+}
+
+/// Temperature unit for `weather.units`
+fn get_unit() -> &'static str {
+    match config::get().weather.units.as_str() {
+        "imperial" => "F",
+        "standard" => "K",
+        _ => "C",
+    }
+}
+
+/// The conditions, temperature, feels-like temperature and humidity of the
+/// latest weather reading
+pub struct WeatherScreen {
+    timezone: SelectedTimezone,
+    reading: Option<Reading>,
+    now: DateTime<Utc>,
+    /// whether `update` follows the weather worker
+    live: bool,
+}
+
+impl WeatherScreen {
+    pub const NAME: &'static str = "weather";
+
+    pub fn new(timezone: SelectedTimezone) -> Self {
+        Self {
+            reading: get_reading(timezone.get()),
+            timezone,
+            now: Utc::now(),
+            live: true,
+        }
+    }
+
+    /// Always shows `reading` as it was at `now`
+    #[cfg(test)]
+    pub fn frozen(reading: Option<Reading>, now: DateTime<Utc>) -> Self {
+        Self {
+            timezone: SelectedTimezone::new(),
+            reading,
+            now,
+            live: false,
+        }
+    }
+}
+
+impl Screen for WeatherScreen {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_available(&self) -> bool {
+        match self.live {
+            true => get_reading(self.timezone.get()).is_some(),
+            false => self.reading.is_some(),
+        }
+    }
+
+    fn update(&mut self, _dt: Duration) {
+        if self.live {
+            self.reading = get_reading(self.timezone.get());
+            self.now = Utc::now();
+        }
+    }
+
+    fn draw(&mut self, canvas: &mut FrameBuffer) {
+        let reading = match &self.reading {
+            Some(x) => x,
+            None => return,
+        };
+        let conditions = &reading.conditions;
+
+        // dimmed once the reading is too old to trust
+        let colour = match reading.is_stale(&self.now) {
+            true => Rgb888::new(0x40, 0x0, 0x0),
+            false => Rgb888::new(0xff, 0x0, 0x0),
+        };
+        let font_small: MonoTextStyle<Rgb888> = MonoTextStyle::new(&FONT_4X6, colour);
+        let font_large: MonoTextStyle<Rgb888> = MonoTextStyle::new(&FONT_7X13, colour);
+
+        let height = canvas.size().height as i32;
+
+        // conditions along the top, temperature in the middle, details at the bottom
+        _ = Text::with_alignment(
+            get_description(conditions.condition),
+            Point::new(1, 6),
+            font_small,
+            Alignment::Left,
+        )
+        .draw(canvas);
+
+        _ = Text::with_alignment(
+            format!("{:.1}{}", conditions.temperature, get_unit()).as_str(),
+            Point::new(1, height / 2 + 4),
+            font_large,
+            Alignment::Left,
+        )
+        .draw(canvas);
+
+        _ = Text::with_alignment(
+            format!(
+                "feels {:.1} {:.0}%",
+                conditions.feels_like, conditions.humidity
+            )
+            .as_str(),
+            Point::new(1, height - 2),
+            font_small,
+            Alignment::Left,
+        )
+        .draw(canvas);
+    }
+}
@@ -0,0 +1,184 @@
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use serde::Deserialize;
+
+use crate::matrix::FrameBuffer;
+
+/// How one screen gives way to the next
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Effect {
+    /// hard cut
+    None,
+    /// the new screen pushes the old one out to the left
+    SlideLeft,
+    SlideRight,
+    SlideUp,
+    SlideDown,
+    Crossfade,
+    /// the new screen is uncovered from left to right
+    Wipe,
+    /// the new screen appears a few pixels at a time
+    Dissolve,
+}
+
+/// Slow at both ends, fast in the middle
+fn ease(progress: f32) -> f32 {
+    let x = progress.clamp(0.0, 1.0);
+    x * x * (3.0 - 2.0 * x)
+}
+
+/// Point in `[0, 1)` at which pixel `i` switches over during a dissolve,
+/// scattered but the same every run
+fn get_dissolve_threshold(i: usize) -> f32 {
+    // 32-bit finaliser from MurmurHash3
+    let mut x = i as u32;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xc2b2_ae35);
+    x ^= x >> 16;
+
+    (x >> 8) as f32 / (1 << 24) as f32
+}
+
+fn mix(from: Rgb888, to: Rgb888, amount: f32) -> Rgb888 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    Rgb888::new(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
+}
+
+/// Renders `effect` part way through, `progress` running from 0 (only
+/// `from` showing) to 1 (only `to` showing). All three buffers must be the
+/// same size
+pub fn blend(
+    effect: Effect,
+    from: &FrameBuffer,
+    to: &FrameBuffer,
+    progress: f32,
+    out: &mut FrameBuffer,
+) {
+    let size = out.size();
+    let (width, height) = (size.width as usize, size.height as usize);
+    let eased = ease(progress);
+
+    // distance the screens have moved along the axis of a slide
+    let offset_x = (eased * width as f32).round() as usize;
+    let offset_y = (eased * height as f32).round() as usize;
+
+    let from = from.pixels();
+    let to = to.pixels();
+
+    for (i, pixel) in out.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+
+        *pixel = match effect {
+            Effect::None => to[i],
+            Effect::SlideLeft => match x + offset_x < width {
+                true => from[y * width + x + offset_x],
+                false => to[y * width + x + offset_x - width],
+            },
+            Effect::SlideRight => match x >= offset_x {
+                true => from[y * width + x - offset_x],
+                false => to[y * width + x + width - offset_x],
+            },
+            Effect::SlideUp => match y + offset_y < height {
+                true => from[(y + offset_y) * width + x],
+                false => to[(y + offset_y - height) * width + x],
+            },
+            Effect::SlideDown => match y >= offset_y {
+                true => from[(y - offset_y) * width + x],
+                false => to[(y + height - offset_y) * width + x],
+            },
+            Effect::Crossfade => mix(from[i], to[i], eased),
+            Effect::Wipe => match x < offset_x {
+                true => to[i],
+                false => from[i],
+            },
+            // linear, easing would bunch most of the pixels up in the middle
+            Effect::Dissolve => match get_dissolve_threshold(i) < progress {
+                true => to[i],
+                false => from[i],
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x2 buffers, `from` numbered 0-7 in the red channel, `to` 1-8 in green
+    fn get_buffers() -> (FrameBuffer, FrameBuffer, FrameBuffer) {
+        let size = Size::new(4, 2);
+        let mut from = FrameBuffer::new(size);
+        let mut to = FrameBuffer::new(size);
+        for i in 0..8 {
+            from.pixels_mut()[i] = Rgb888::new(i as u8, 0, 0);
+            to.pixels_mut()[i] = Rgb888::new(0, i as u8 + 1, 0);
+        }
+
+        (from, to, FrameBuffer::new(size))
+    }
+
+    /// `f` for a pixel of `from`, `t` for `to`, followed by its index
+    fn describe(frame: &FrameBuffer) -> Vec<String> {
+        frame
+            .pixels()
+            .iter()
+            .map(|x| match x.g() {
+                0 => format!("f{}", x.r()),
+                _ => format!("t{}", x.g() - 1),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slides_move_both_screens() {
+        let (from, to, mut out) = get_buffers();
+
+        blend(Effect::SlideLeft, &from, &to, 0.5, &mut out);
+        assert_eq!(
+            describe(&out),
+            vec!["f2", "f3", "t0", "t1", "f6", "f7", "t4", "t5"]
+        );
+
+        blend(Effect::SlideDown, &from, &to, 0.5, &mut out);
+        assert_eq!(
+            describe(&out),
+            vec!["t4", "t5", "t6", "t7", "f0", "f1", "f2", "f3"]
+        );
+    }
+
+    #[test]
+    fn every_effect_starts_and_ends_on_a_screen() {
+        let (from, to, mut out) = get_buffers();
+
+        for effect in [
+            Effect::SlideLeft,
+            Effect::SlideRight,
+            Effect::SlideUp,
+            Effect::SlideDown,
+            Effect::Crossfade,
+            Effect::Wipe,
+            Effect::Dissolve,
+        ] {
+            blend(effect, &from, &to, 0.0, &mut out);
+            assert_eq!(out, from, "{:?} at 0", effect);
+            blend(effect, &from, &to, 1.0, &mut out);
+            assert_eq!(out, to, "{:?} at 1", effect);
+        }
+    }
+
+    #[test]
+    fn crossfade_mixes_colours() {
+        let (mut from, mut to, mut out) = get_buffers();
+        from.pixels_mut()[0] = Rgb888::new(200, 0, 0);
+        to.pixels_mut()[0] = Rgb888::new(0, 100, 0);
+
+        blend(Effect::Crossfade, &from, &to, 0.5, &mut out);
+        assert_eq!(out.pixels()[0], Rgb888::new(100, 50, 0));
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use chrono_tz::Tz;
+
+use super::{Conditions, WeatherProvider};
+
+/// Reads conditions from a local JSON file on every lookup, so tests and
+/// offline demos work without a network. The file is a `Conditions` object,
+/// see `tests/fixtures/weather.json`
+pub struct FixtureProvider {
+    path: PathBuf,
+}
+
+impl FixtureProvider {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl WeatherProvider for FixtureProvider {
+    fn fetch(&mut self, _timezone: &Tz) -> Result<Conditions, String> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("could not read {}: {}", self.path.display(), e))?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("invalid fixture {}: {}", self.path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weather::Condition;
+
+    #[test]
+    fn example_fixture_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/weather.json");
+
+        let conditions = FixtureProvider::new(&path)
+            .fetch(&chrono_tz::Australia::Adelaide)
+            .unwrap();
+        assert_eq!(conditions.temperature, 21.5);
+        assert_eq!(conditions.condition, Condition::PartlyCloudy);
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::config::{self, WeatherConfig};
+use crate::states::SelectedTimezone;
+
+mod fixture;
+mod open_meteo;
+mod openweathermap;
+
+pub use fixture::FixtureProvider;
+pub use open_meteo::OpenMeteoProvider;
+pub use openweathermap::OpenWeatherMapProvider;
+
+/// A source of current weather conditions, implementations may block
+pub trait WeatherProvider: Send {
+    fn fetch(&mut self, timezone: &Tz) -> Result<Conditions, String>;
+}
+
+/// Which `WeatherProvider` the `weather.provider` config key selects
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderKind {
+    #[serde(rename = "openweathermap")]
+    OpenWeatherMap,
+    OpenMeteo,
+    Fixture,
+}
+
+/// Broad weather condition, common to every provider
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+    Unknown,
+}
+
+/// Current weather in `weather.units`, whichever provider it came from
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Conditions {
+    pub temperature: f64,
+    pub feels_like: f64,
+    /// relative humidity as a percentage
+    pub humidity: f64,
+    pub condition: Condition,
+    pub wind_speed: f64,
+    /// degrees clockwise from north the wind blows from
+    pub wind_direction: f64,
+    pub sunrise: Option<DateTime<Utc>>,
+    pub sunset: Option<DateTime<Utc>>,
+}
+
+/// Last successful weather lookup
+#[derive(Clone, PartialEq, Debug)]
+pub struct Reading {
+    pub conditions: Conditions,
+    /// timezone whose city the reading was taken for
+    pub timezone: Tz,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Reading {
+    /// Whether the reading is older than `weather.stale_after_ms` at `now`
+    pub fn is_stale<T: chrono::TimeZone>(&self, now: &DateTime<T>) -> bool {
+        let age = now.with_timezone(&Utc) - self.fetched_at;
+        age.num_milliseconds() > config::get().weather.stale_after_ms as i64
+    }
+}
+
+lazy_static! {
+    static ref CURRENT_READING: Mutex<Option<Reading>> = Mutex::new(None);
+}
+
+/// Cached reading for `timezone`, never blocks on the network
+pub fn get_reading(timezone: Tz) -> Option<Reading> {
+    match &*CURRENT_READING.lock().unwrap() {
+        Some(x) if x.timezone == timezone => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// Weather services expect `New York` rather than the `America/New_York` tz name
+fn get_city(timezone: &Tz) -> String {
+    let name = timezone.name();
+    name.rsplit('/').next().unwrap_or(name).replace('_', " ")
+}
+
+/// The configured provider, `None` when it can't be used as configured
+pub fn new_provider(config: &WeatherConfig) -> Option<Box<dyn WeatherProvider>> {
+    match config.provider {
+        ProviderKind::OpenWeatherMap => match &config.api_key {
+            Some(x) => Some(Box::new(OpenWeatherMapProvider::new(
+                x,
+                &config.units,
+                &config.language,
+            ))),
+            None => None,
+        },
+        ProviderKind::OpenMeteo => Some(Box::new(OpenMeteoProvider::new(&config.units))),
+        ProviderKind::Fixture => match &config.fixture_path {
+            Some(x) => Some(Box::new(FixtureProvider::new(x))),
+            None => None,
+        },
+    }
+}
+
+/// Refreshes the cached reading on a background thread every
+/// `weather.refresh_interval_ms`, or straight away when the timezone
+/// changes. Nothing is fetched until a region has been picked or restored.
+/// Does nothing when the provider can't be used, eg. OpenWeatherMap without
+/// an API key
+pub fn start_worker(selected: SelectedTimezone) {
+    let mut provider = match new_provider(&config::get().weather) {
+        Some(x) => x,
+        None => return,
+    };
+
+    std::thread::spawn(move || {
+        let weather_config = &config::get().weather;
+        let refresh_interval = Duration::from_millis(weather_config.refresh_interval_ms);
+        let retry_interval = Duration::from_millis(weather_config.retry_interval_ms);
+
+        let mut next_fetch = Instant::now();
+        let mut last_timezone: Option<Tz> = None;
+
+        loop {
+            let timezone = match selected.get_selected() {
+                Some(x) => x,
+                None => {
+                    sleep(Duration::from_secs(1));
+                    continue;
+                }
+            };
+
+            if Instant::now() >= next_fetch || last_timezone != Some(timezone) {
+                last_timezone = Some(timezone);
+
+                match provider.fetch(&timezone) {
+                    Ok(x) => {
+                        *CURRENT_READING.lock().unwrap() = Some(Reading {
+                            conditions: x,
+                            timezone,
+                            fetched_at: Utc::now(),
+                        });
+                        next_fetch = Instant::now() + refresh_interval;
+                    }
+                    Err(e) => {
+                        // keep serving the last good reading until it goes stale
+                        eprintln!("weather lookup for {} failed: {}", timezone.name(), e);
+                        next_fetch = Instant::now() + retry_interval;
+                    }
+                }
+            }
+
+            sleep(Duration::from_secs(1));
+        }
+    });
+}
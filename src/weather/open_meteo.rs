@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use super::{get_city, Condition, Conditions, WeatherProvider};
+
+// docs: https://open-meteo.com/en/docs, https://open-meteo.com/en/docs/geocoding-api
+
+static GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+static FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+static REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// open-meteo.com forecasts, no API key needed
+pub struct OpenMeteoProvider {
+    units: String,
+    client: reqwest::blocking::Client,
+    /// latitude, longitude of every city looked up so far
+    locations: HashMap<Tz, (f64, f64)>,
+}
+
+#[derive(Deserialize)]
+struct GeocodingResponse {
+    results: Option<Vec<GeocodingResult>>,
+}
+
+#[derive(Deserialize)]
+struct GeocodingResult {
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+    current: Current,
+    daily: Daily,
+}
+
+#[derive(Deserialize)]
+struct Current {
+    temperature_2m: f64,
+    apparent_temperature: f64,
+    relative_humidity_2m: f64,
+    weather_code: u32,
+    wind_speed_10m: f64,
+    wind_direction_10m: f64,
+}
+
+#[derive(Deserialize)]
+struct Daily {
+    sunrise: Vec<i64>,
+    sunset: Vec<i64>,
+}
+
+/// WMO weather interpretation codes
+fn get_condition(code: u32) -> Condition {
+    match code {
+        0 => Condition::Clear,
+        1 | 2 => Condition::PartlyCloudy,
+        3 => Condition::Cloudy,
+        45 | 48 => Condition::Fog,
+        51..=57 => Condition::Drizzle,
+        61..=67 | 80..=82 => Condition::Rain,
+        71..=77 | 85 | 86 => Condition::Snow,
+        95..=99 => Condition::Thunderstorm,
+        _ => Condition::Unknown,
+    }
+}
+
+/// Open-Meteo has no kelvin, so `standard` units are converted from celsius
+fn to_conditions(response: &ForecastResponse, units: &str) -> Conditions {
+    let current = &response.current;
+    let offset = match units {
+        "standard" => 273.15,
+        _ => 0.0,
+    };
+
+    Conditions {
+        temperature: current.temperature_2m + offset,
+        feels_like: current.apparent_temperature + offset,
+        humidity: current.relative_humidity_2m,
+        condition: get_condition(current.weather_code),
+        wind_speed: current.wind_speed_10m,
+        wind_direction: current.wind_direction_10m,
+        sunrise: response
+            .daily
+            .sunrise
+            .first()
+            .and_then(|x| Utc.timestamp_opt(*x, 0).single()),
+        sunset: response
+            .daily
+            .sunset
+            .first()
+            .and_then(|x| Utc.timestamp_opt(*x, 0).single()),
+    }
+}
+
+impl OpenMeteoProvider {
+    /// `units` is `metric`, `imperial` or `standard`, like OpenWeatherMap
+    pub fn new(units: &str) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap();
+
+        Self {
+            units: String::from(units),
+            client,
+            locations: HashMap::new(),
+        }
+    }
+
+    fn get_location(&mut self, timezone: &Tz) -> Result<(f64, f64), String> {
+        if let Some(x) = self.locations.get(timezone) {
+            return Ok(*x);
+        }
+
+        let city = get_city(timezone);
+        let response: GeocodingResponse = self
+            .client
+            .get(GEOCODING_URL)
+            .query(&[("name", city.as_str()), ("count", "1")])
+            .send()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.json())
+            .map_err(|e| e.to_string())?;
+
+        let location = match response.results.as_ref().and_then(|x| x.first()) {
+            Some(x) => (x.latitude, x.longitude),
+            None => return Err(format!("no location found for {}", city)),
+        };
+        self.locations.insert(*timezone, location);
+
+        Ok(location)
+    }
+}
+
+impl WeatherProvider for OpenMeteoProvider {
+    fn fetch(&mut self, timezone: &Tz) -> Result<Conditions, String> {
+        let (latitude, longitude) = self.get_location(timezone)?;
+
+        let (temperature_unit, wind_speed_unit) = match self.units.as_str() {
+            "imperial" => ("fahrenheit", "mph"),
+            _ => ("celsius", "ms"),
+        };
+
+        let response: ForecastResponse = self
+            .client
+            .get(FORECAST_URL)
+            .query(&[
+                ("latitude", latitude.to_string().as_str()),
+                ("longitude", longitude.to_string().as_str()),
+                (
+                    "current",
+                    "temperature_2m,apparent_temperature,relative_humidity_2m,\
+                     weather_code,wind_speed_10m,wind_direction_10m",
+                ),
+                ("daily", "sunrise,sunset"),
+                ("forecast_days", "1"),
+                ("timeformat", "unixtime"),
+                ("temperature_unit", temperature_unit),
+                ("wind_speed_unit", wind_speed_unit),
+            ])
+            .send()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.json())
+            .map_err(|e| e.to_string())?;
+
+        Ok(to_conditions(&response, &self.units))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static RESPONSE: &str = r#"{
+        "latitude": -34.875,
+        "longitude": 138.625,
+        "current": {
+            "time": 1664943300,
+            "interval": 900,
+            "temperature_2m": 21.5,
+            "apparent_temperature": 20.9,
+            "relative_humidity_2m": 48,
+            "weather_code": 61,
+            "wind_speed_10m": 4.6,
+            "wind_direction_10m": 220
+        },
+        "daily": {
+            "time": [1664890200],
+            "sunrise": [1664912409],
+            "sunset": [1664957618]
+        }
+    }"#;
+
+    #[test]
+    fn response_is_normalised() {
+        let response: ForecastResponse = serde_json::from_str(RESPONSE).unwrap();
+
+        let conditions = to_conditions(&response, "metric");
+        assert_eq!(conditions.temperature, 21.5);
+        assert_eq!(conditions.humidity, 48.0);
+        assert_eq!(conditions.condition, Condition::Rain);
+        assert_eq!(conditions.sunset.unwrap().timestamp(), 1664957618);
+    }
+
+    #[test]
+    fn standard_units_are_kelvin() {
+        let response: ForecastResponse = serde_json::from_str(RESPONSE).unwrap();
+
+        let conditions = to_conditions(&response, "standard");
+        assert!((conditions.temperature - 294.65).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,100 @@
+use ::openweathermap::blocking::weather;
+use ::openweathermap::CurrentWeather;
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+
+use super::{get_city, Condition, Conditions, WeatherProvider};
+
+/// openweathermap.org current weather, needs an API key
+pub struct OpenWeatherMapProvider {
+    api_key: String,
+    units: String,
+    language: String,
+}
+
+impl OpenWeatherMapProvider {
+    pub fn new(api_key: &str, units: &str, language: &str) -> Self {
+        Self {
+            api_key: String::from(api_key),
+            units: String::from(units),
+            language: String::from(language),
+        }
+    }
+}
+
+/// https://openweathermap.org/weather-conditions
+fn get_condition(id: u64) -> Condition {
+    match id {
+        200..=299 => Condition::Thunderstorm,
+        300..=399 => Condition::Drizzle,
+        500..=599 => Condition::Rain,
+        600..=699 => Condition::Snow,
+        700..=799 => Condition::Fog,
+        800 => Condition::Clear,
+        801 | 802 => Condition::PartlyCloudy,
+        803 | 804 => Condition::Cloudy,
+        _ => Condition::Unknown,
+    }
+}
+
+fn to_conditions(current_weather: &CurrentWeather) -> Conditions {
+    Conditions {
+        temperature: current_weather.main.temp,
+        feels_like: current_weather.main.feels_like,
+        humidity: current_weather.main.humidity,
+        condition: match current_weather.weather.first() {
+            Some(x) => get_condition(x.id),
+            None => Condition::Unknown,
+        },
+        wind_speed: current_weather.wind.speed,
+        wind_direction: current_weather.wind.deg,
+        sunrise: Utc.timestamp_opt(current_weather.sys.sunrise, 0).single(),
+        sunset: Utc.timestamp_opt(current_weather.sys.sunset, 0).single(),
+    }
+}
+
+impl WeatherProvider for OpenWeatherMapProvider {
+    fn fetch(&mut self, timezone: &Tz) -> Result<Conditions, String> {
+        let current_weather = weather(
+            &get_city(timezone),
+            &self.units,
+            &self.language,
+            &self.api_key,
+        )?;
+
+        Ok(to_conditions(&current_weather))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_is_normalised() {
+        let response = r#"{
+            "coord": {"lon": 138.6, "lat": -34.93},
+            "weather": [{"id": 803, "main": "Clouds", "description": "broken clouds", "icon": "04d"}],
+            "base": "stations",
+            "main": {"temp": 21.5, "feels_like": 20.9, "temp_min": 19.0, "temp_max": 23.1, "pressure": 1016, "humidity": 48},
+            "visibility": 10000,
+            "wind": {"speed": 4.6, "deg": 220},
+            "clouds": {"all": 75},
+            "dt": 1664943462,
+            "sys": {"type": 2, "id": 2001763, "country": "AU", "sunrise": 1664912409, "sunset": 1664957618},
+            "timezone": 37800,
+            "id": 2078025,
+            "name": "Adelaide",
+            "cod": 200
+        }"#;
+        let current_weather: CurrentWeather = serde_json::from_str(response).unwrap();
+
+        let conditions = to_conditions(&current_weather);
+        assert_eq!(conditions.temperature, 21.5);
+        assert_eq!(conditions.feels_like, 20.9);
+        assert_eq!(conditions.humidity, 48.0);
+        assert_eq!(conditions.condition, Condition::Cloudy);
+        assert_eq!(conditions.wind_direction, 220.0);
+        assert_eq!(conditions.sunrise.unwrap().timestamp(), 1664912409);
+    }
+}